@@ -1,6 +1,8 @@
 use std::collections::{HashSet, HashMap};
 
 use toki_sama::pu::{Pu, TokiPonaWord};
+use toki_sama::lemma::lemmatize;
+use toki_sama::fallback::Associations;
 
 // "Low value" words for discovering compound nounds
 pub const ignore_words : [&str; 5] = [
@@ -65,8 +67,9 @@ impl TranslationBag {
         // Todo dont constrcut each time
         let ignore_toki_pona : Vec<_> = ignore_words.iter().flat_map(|x| pu.lookup(x)).collect();
 
+        // Count inflections together, "hunted" and "hunting" are both "hunt"
         for word in english.split_whitespace() {
-            english_words.incr(&word.to_owned(), 1);
+            english_words.incr(&lemmatize(word), 1);
         }
 
         for toki_str in toki_pona.split_whitespace() {
@@ -123,7 +126,7 @@ impl Analysis {
     }
 
     pub fn lookup(&self, english : &str) -> Vec<(TokiPonaWord, f64)> {
-        let english_normalized = english.to_ascii_lowercase();
+        let english_normalized = lemmatize(english);
 
         let mut counts = CountMap::new();
 
@@ -146,3 +149,9 @@ impl Analysis {
     }
 }
 
+// Lets toki_sama propose compounds for English words it has no translation for
+impl Associations for Analysis {
+    fn associations(&self, english : &str) -> Vec<(TokiPonaWord, f64)> {
+        self.lookup(english)
+    }
+}
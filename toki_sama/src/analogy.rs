@@ -0,0 +1,146 @@
+// Analogies by compound arithmetic, "man : woman :: boy : ?".
+// Whatever words change between the translations of the first pair are changed
+// in the translation of the third word, then we search near the result.
+//
+//   man (mije) : woman (meli) :: boy (mije lili) : ? -> meli lili -> girl
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::{Pu, TokiPonaWord};
+use crate::query::QueryError;
+use crate::{CompoundWord, ThesaurusResult, TokiSama, WordGloss};
+
+// Analogies are written "a:b::c:?", the trailing "?" is optional.
+pub fn is_analogy(input: &str) -> bool {
+    input.contains("::")
+}
+
+pub fn parse(input: &str) -> Option<(&str, &str, &str)> {
+    let (left, right) = input.split_once("::")?;
+    let (a, b) = left.split_once(':')?;
+    let c = match right.split_once(':') {
+        Some((c, unknown)) if unknown.trim() == "?" || unknown.trim().is_empty() => c,
+        Some(_) => return None,
+        None => right,
+    };
+
+    let (a, b, c) = (a.trim(), b.trim(), c.trim());
+    if (a.is_empty() || b.is_empty() || c.is_empty()) {
+        return None;
+    }
+
+    Some((a, b, c))
+}
+
+// One change made to the third word's translation.
+// A word with nothing to replace it is dropped, and a word with nothing to replace is added.
+#[derive(Debug, Serialize)]
+pub struct Substitution {
+    from: Option<WordGloss>,
+    to: Option<WordGloss>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalogyResult {
+    pub(crate) substitutions: Vec<Substitution>,
+    pub(crate) toki_pona_string: String,
+    pub(crate) similar: Vec<ThesaurusResult>,
+}
+
+impl TokiSama {
+    pub fn analogy(&self, a: &str, b: &str, c: &str, options: &SearchOptions, pu: &Pu) -> Result<AnalogyResult, QueryError> {
+        // Only the words as typed, a near miss would quietly answer a different analogy
+        let resolve = |english: &str| self.best_entry(english, options).ok_or_else(|| QueryError::UnknownWord(english.to_owned()));
+        let a_toki_pona = &self.dictionary.entries[resolve(a)?].toki_pona;
+        let b_toki_pona = &self.dictionary.entries[resolve(b)?].toki_pona;
+        let c_rank = resolve(c)?;
+        let c_toki_pona = &self.dictionary.entries[c_rank].toki_pona;
+
+        let removed = a_toki_pona.words.difference(&b_toki_pona.words);
+        let mut added = b_toki_pona.content_words()
+            .filter(|tp| !a_toki_pona.words.contains(*tp) && !c_toki_pona.words.contains(*tp));
+
+        // Replace removed words in place so the result keeps the shape of c, "mije lili" -> "meli lili"
+        let mut substitutions = Vec::new();
+        let mut toki_pona: smallvec::SmallVec<[TokiPonaWord; 4]> = smallvec::SmallVec::new();
+        for tp in c_toki_pona.toki_pona.iter().cloned() {
+            if (!c_toki_pona.words.contains(tp) || !removed.contains(tp)) {
+                toki_pona.push(tp);
+                continue;
+            }
+
+            let replacement = added.next();
+            toki_pona.extend(replacement);
+            substitutions.push(Substitution {
+                from: Some(WordGloss::new(tp, pu)),
+                to: replacement.map(|x| WordGloss::new(x, pu)),
+            });
+        }
+
+        for tp in added {
+            toki_pona.push(tp);
+            substitutions.push(Substitution {
+                from: None,
+                to: Some(WordGloss::new(tp, pu)),
+            });
+        }
+
+        let compound = CompoundWord::with_structure(toki_pona, pu.lookup("pi"));
+        if (compound.len() == 0) {
+            return Err(QueryError::EmptyCompound);
+        }
+
+        // The words in the question are never the answer
+        let asked = [a, b, c];
+        let similar = self.ranked_candidates(&compound, Some(c_rank), options)
+            .iter()
+            .filter(|candidate| {
+                let english = &self.dictionary.entries[candidate.entry_rank].english;
+                !asked.iter().any(|x| x.eq_ignore_ascii_case(english))
+            })
+            .take(options.similar)
+            .map(|candidate| self.thesaurus_result(candidate, &compound, pu))
+            .collect();
+
+        Ok(AnalogyResult {
+            substitutions,
+            toki_pona_string: compound.to_string(pu),
+            similar,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn analogy_substitutes_words() {
+        assert_eq!(Some(("king", "queen", "man")), parse("king:queen::man:?"));
+        assert_eq!(Some(("king", "queen", "man")), parse(" king : queen :: man "));
+        assert_eq!(None, parse("king:queen"));
+
+        let pu = Pu::from_subset(&[("jan", "person"), ("mije", "man"), ("meli", "woman"), ("lili", "small")]);
+        let lines = [
+            "mije: [man 90]",
+            "meli: [woman 90]",
+            "jan lili: [child 80]",
+            "mije lili: [boy 70]",
+            "meli lili: [girl 70]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let result = toki_sama.analogy("man", "woman", "boy", &SearchOptions::default(), &pu).unwrap();
+        assert_eq!("meli lili", result.toki_pona_string);
+        assert_eq!(1, result.substitutions.len());
+        assert_eq!("girl", result.similar[0].english);
+
+        assert_eq!(Err(QueryError::UnknownWord("zebra".to_owned())), toki_sama.analogy("man", "woman", "zebra", &SearchOptions::default(), &pu).map(|_| ()));
+
+        // Inflections are fine, words that only start the same are not
+        assert!(toki_sama.analogy("man", "woman", "boys", &SearchOptions::default(), &pu).is_ok());
+        assert_eq!(Err(QueryError::UnknownWord("bo".to_owned())), toki_sama.analogy("man", "woman", "bo", &SearchOptions::default(), &pu).map(|_| ()));
+    }
+}
@@ -0,0 +1,203 @@
+// Antonym search.
+// toki pona makes opposites by adding "ala", or by swapping a word for its contrasting pair,
+// "lili" for "suli", "ike" for "pona". We apply each of those to the translation of a word
+// and look for English words near whatever comes out.
+//
+//   big (suli) -> swap suli for lili -> small, little
+//   happy (pilin pona) -> add ala -> sad, or swap pona for ike -> upset
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::{Pu, TokiPonaWord};
+use crate::{CompoundWord, ThesaurusResult, TokiSama};
+
+// Pairs of contrasting toki pona words, one pair per line eg "suli lili".
+// Lines starting with '#' are comments.
+#[derive(Debug, Default)]
+pub struct Opposites {
+    pairs: Vec<(TokiPonaWord, TokiPonaWord)>,
+}
+
+impl Opposites {
+    pub fn from_lines(lines: &[String], pu: &Pu) -> Self {
+        let mut pairs = Vec::with_capacity(lines.len());
+
+        for line in lines {
+            let line = line.trim();
+            if (line.is_empty() || line.starts_with('#')) {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [x, y] => {
+                    match (pu.lookup(x), pu.lookup(y)) {
+                        (Some(x), Some(y)) => pairs.push((x, y)),
+                        _ => println!("Unknown toki pona word in opposites: {}", line),
+                    }
+                }
+                _ => println!("Could not parse opposites line: {}", line),
+            }
+        }
+
+        Opposites {
+            pairs,
+        }
+    }
+
+    pub fn read(path: &Path, pu: &Pu) -> Self {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        let lines: Vec<String> = reader.lines().map(|x| x.unwrap()).collect();
+
+        Self::from_lines(&lines, pu)
+    }
+
+    // Every word contrasting with the given one, a word can be in more than one pair.
+    pub fn opposites_of(&self, word: TokiPonaWord) -> impl Iterator<Item = TokiPonaWord> + '_ {
+        self.pairs.iter().filter_map(move |(x, y)| {
+            if (*x == word) {
+                Some(*y)
+            }
+            else if (*y == word) {
+                Some(*x)
+            }
+            else {
+                None
+            }
+        })
+    }
+
+    // Each single change that negates a compound, toggling "ala" and then swapping each word with an opposite.
+    pub fn transformations(&self, toki_pona: &CompoundWord, pu: &Pu) -> Vec<(Transformation, CompoundWord)> {
+        let mut transformed = Vec::new();
+        let pi = pu.lookup("pi");
+
+        if let Some(ala) = pu.lookup("ala") {
+            if (toki_pona.words.contains(ala)) {
+                let mut without: smallvec::SmallVec<[TokiPonaWord; 4]> = toki_pona.toki_pona.iter().cloned().filter(|tp| *tp != ala).collect();
+
+                // Don't leave a "pi" with nothing to group
+                if (without.len() > 1 && without.last().cloned() == pi) {
+                    without.pop();
+                }
+
+                if (!without.is_empty()) {
+                    transformed.push((Transformation::RemoveAla, CompoundWord::with_structure(without, pi)));
+                }
+            }
+            else {
+                let mut with = toki_pona.toki_pona.clone();
+                with.push(ala);
+                transformed.push((Transformation::AddAla, CompoundWord::with_structure(with, pi)));
+            }
+        }
+
+        for (i, tp) in toki_pona.toki_pona.iter().enumerate() {
+            if (!toki_pona.words.contains(*tp)) {
+                continue;
+            }
+
+            for opposite in self.opposites_of(*tp) {
+                let mut swapped = toki_pona.toki_pona.clone();
+                swapped[i] = opposite;
+
+                let transformation = Transformation::Swap {
+                    from: pu.get(tp).to_owned(),
+                    to: pu.get(&opposite).to_owned(),
+                };
+
+                transformed.push((transformation, CompoundWord::with_structure(swapped, pi)));
+            }
+        }
+
+        transformed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Transformation {
+    AddAla,
+    RemoveAla,
+    Swap { from: String, to: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Antonym {
+    transformation: Transformation,
+    // The negated compound the result was found near
+    transformed_string: String,
+    #[serde(flatten)]
+    result: ThesaurusResult,
+}
+
+impl TokiSama {
+    // English words near each negation of the translation of a word, closest first.
+    // None if neither the word nor an inflection of it is in the dictionary.
+    pub fn antonyms(&self, english: &str, opposites: &Opposites, options: &SearchOptions, pu: &Pu) -> Option<Vec<Antonym>> {
+        let entry_rank = self.best_entry(english, options)?;
+        let entry = &self.dictionary.entries[entry_rank];
+
+        let mut seen = HashSet::new();
+        seen.insert(entry.english.as_str());
+
+        let mut antonyms = Vec::new();
+        for (transformation, transformed) in opposites.transformations(&entry.toki_pona, pu) {
+            let candidates = self.ranked_candidates(&transformed, Some(entry_rank), options);
+            let new_candidates = candidates.iter()
+                // Closer to the negation than to the word itself, so "suli ala" doesn't find "large"
+                .filter(|c| self.dictionary.entries[c.entry_rank].toki_pona.dist(&entry.toki_pona) > c.dist)
+                .filter(|c| seen.insert(self.dictionary.entries[c.entry_rank].english.as_str()))
+                .take(options.similar);
+
+            for candidate in new_candidates {
+                antonyms.push(Antonym {
+                    transformation: transformation.clone(),
+                    transformed_string: transformed.to_string(pu),
+                    result: self.thesaurus_result(candidate, &transformed, pu),
+                });
+            }
+        }
+
+        // Stable, so ties keep the order of the transformations
+        antonyms.sort_by(|x, y| x.result.score.partial_cmp(&y.result.score).unwrap().then(x.result.dist.cmp(&y.result.dist)));
+
+        Some(antonyms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn antonym_transformations() {
+        let pu = Pu::from_subset(&[("pilin", "feeling"), ("pona", "good"), ("ike", "bad"), ("ala", "no")]);
+        let opposites = Opposites::from_lines(&["# comment".to_owned(), "pona ike".to_owned()], &pu);
+        let describe = |toki_pona: &str| -> Vec<(Transformation, String)> {
+            let compound = CompoundWord::parse(toki_pona, &pu).unwrap();
+            opposites.transformations(&compound, &pu).into_iter().map(|(t, c)| (t, c.to_string(&pu))).collect()
+        };
+
+        let swap = Transformation::Swap { from: "pona".to_owned(), to: "ike".to_owned() };
+        assert_eq!(vec![
+            (Transformation::AddAla, "pilin pona ala".to_owned()),
+            (swap, "pilin ike".to_owned()),
+        ], describe("pilin pona"));
+        assert_eq!(vec![(Transformation::RemoveAla, "pilin".to_owned())], describe("pilin ala"));
+
+        let toki_sama = toki_sama(&pu, &["pilin pona: [happy 80]", "pilin ike: [sad 70]"]);
+        let antonyms = toki_sama.antonyms("happy", &opposites, &SearchOptions::default(), &pu).unwrap();
+        assert_eq!("sad", antonyms[0].result.english);
+
+        // Not the antonyms of whatever "hap" completes to
+        assert!(toki_sama.antonyms("hap", &opposites, &SearchOptions::default(), &pu).is_none());
+    }
+}
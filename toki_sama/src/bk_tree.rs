@@ -0,0 +1,170 @@
+use crate::CompoundWord;
+
+// A BK-tree over compound words, keyed on the set distance between them.
+// Lots of English words share a translation so each node holds every
+// entry rank with that exact compound, and queries only have to visit
+// children whose edge distance is within the search radius.
+#[derive(Debug, Default)]
+pub struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+#[derive(Debug)]
+struct BkNode {
+    toki_pona: CompoundWord,
+    entry_ranks: Vec<usize>,
+    children: Vec<(u32, usize)>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        BkTree {
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn insert(&mut self, toki_pona: &CompoundWord, entry_rank: usize) {
+        if (self.nodes.is_empty()) {
+            self.nodes.push(BkNode::new(toki_pona, entry_rank));
+            return;
+        }
+
+        let mut cur = 0;
+        loop {
+            let dist = self.nodes[cur].toki_pona.dist(toki_pona);
+            if (dist == 0) {
+                self.nodes[cur].entry_ranks.push(entry_rank);
+                return;
+            }
+
+            let child = self.nodes[cur].children.iter()
+                .find(|(d, _)| *d == dist)
+                .map(|(_, node)| *node);
+
+            match child {
+                Some(next) => {
+                    cur = next;
+                }
+                None => {
+                    let new_node = self.nodes.len();
+                    self.nodes.push(BkNode::new(toki_pona, entry_rank));
+                    self.nodes[cur].children.push((dist, new_node));
+                    return;
+                }
+            }
+        }
+    }
+
+    // All entry ranks within max_dist of the query, along with their distance.
+    // Results are in no particular order.
+    pub fn find_within(&self, toki_pona: &CompoundWord, max_dist: u32) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+
+        for (node, dist) in self.find_nodes_within(toki_pona, max_dist) {
+            for entry_rank in &self.nodes[node].entry_ranks {
+                found.push((*entry_rank, dist));
+            }
+        }
+
+        found
+    }
+
+    // Like find_within but one result per distinct compound, as a node index.
+    pub fn find_nodes_within(&self, toki_pona: &CompoundWord, max_dist: u32) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+
+        if (self.nodes.is_empty()) {
+            return found;
+        }
+
+        let mut stack = vec![0];
+        while let Some(cur) = stack.pop() {
+            let node = &self.nodes[cur];
+            let dist = node.toki_pona.dist(toki_pona);
+
+            if (dist <= max_dist) {
+                found.push((cur, dist));
+            }
+
+            // Triangle inequality, anything under a child edge d is at least |d - dist| away.
+            let lo = dist.saturating_sub(max_dist);
+            let hi = dist + max_dist;
+            for (edge_dist, child) in &node.children {
+                if (*edge_dist >= lo && *edge_dist <= hi) {
+                    stack.push(*child);
+                }
+            }
+        }
+
+        found
+    }
+
+    pub fn node_word(&self, node: usize) -> &CompoundWord {
+        &self.nodes[node].toki_pona
+    }
+
+    // Entry ranks sharing this node's compound, best ranked first
+    pub fn node_entries(&self, node: usize) -> &[usize] {
+        &self.nodes[node].entry_ranks
+    }
+}
+
+impl BkNode {
+    fn new(toki_pona: &CompoundWord, entry_rank: usize) -> Self {
+        BkNode {
+            toki_pona: toki_pona.clone(),
+            entry_ranks: vec![entry_rank],
+            children: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pu::Pu;
+    use crate::tests::entries;
+
+    #[test]
+    fn bk_tree_matches_linear_scan() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("soweli", "animal"), ("lipu", "paper"), ("moku", "food")]);
+        let lines = [
+            "toki musi: [poetry 50, joke 40]",
+            "toki: [talk 90]",
+            "musi: [game 80]",
+            "soweli: [cat 70]",
+            "soweli musi: [pet 30]",
+            "lipu moku: [menu 50]",
+            "lipu toki musi: [comic 20]",
+        ];
+
+        let entries = entries(&pu, &lines);
+
+        let mut tree = BkTree::new();
+        for (i, entry) in entries.iter().enumerate() {
+            tree.insert(&entry.toki_pona, i);
+        }
+
+        for query in &entries {
+            for max_dist in 0..4 {
+                let mut found = tree.find_within(&query.toki_pona, max_dist);
+                found.sort();
+
+                let expected: Vec<_> = entries.iter().enumerate()
+                    .map(|(i, e)| (i, e.toki_pona.dist(&query.toki_pona)))
+                    .filter(|(_, d)| *d <= max_dist)
+                    .collect();
+
+                assert_eq!(expected, found);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+use crate::pu::TokiPonaWord;
+use crate::word_set::WordSet;
+use crate::{CompoundWord, Translation};
+
+// How far apart two compound words are, smaller is closer.
+// The first argument is the word being searched for, some metrics aren't symmetric.
+pub trait Distance {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64;
+}
+
+// Number of words in one compound but not the other.
+pub struct SymmetricDifference;
+
+impl Distance for SymmetricDifference {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64 {
+        query.words.dist(&other.words) as f64
+    }
+}
+
+// Symmetric difference relative to the size of the union,
+// so sharing one word out of two counts for more than one out of five.
+pub struct Jaccard;
+
+impl Distance for Jaccard {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64 {
+        let union = query.words.union(&other.words).len();
+        if (union == 0) {
+            return 0.0;
+        }
+
+        query.words.dist(&other.words) as f64 / union as f64
+    }
+}
+
+// Fraction of the query's words missing from the other compound.
+// Extra words in the other compound are free when ranking, so "toki" is fully contained in "toki musi".
+// Candidates are still found by symmetric difference though, so extra words count towards max_dist.
+pub struct Containment;
+
+impl Distance for Containment {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64 {
+        let len = query.words.len();
+        if (len == 0) {
+            return 0.0;
+        }
+
+        query.words.difference(&other.words).len() as f64 / len as f64
+    }
+}
+
+// Symmetric difference with each word weighted by its inverse document frequency.
+// Disagreeing on a rare word like "kijetesantakalu" costs a lot more than on "jan".
+pub struct Specificity {
+    weights: Vec<f64>,
+}
+
+impl Specificity {
+    pub fn new(entries: &[Translation]) -> Self {
+        let mut counts = vec![0u32; WordSet::CAPACITY];
+        for entry in entries {
+            for tp in entry.toki_pona.words.iter() {
+                counts[tp.index()] += 1;
+            }
+        }
+
+        let n = entries.len() as f64;
+        let weights = counts.iter().map(|df| ((1.0 + n) / (1.0 + *df as f64)).ln() + 1.0).collect();
+
+        Specificity {
+            weights,
+        }
+    }
+
+    pub fn weight(&self, word: TokiPonaWord) -> f64 {
+        self.weights[word.index()]
+    }
+}
+
+impl Distance for Specificity {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64 {
+        query.words.symmetric_difference(&other.words).iter().fold(0.0, |acc, tp| acc + self.weight(tp))
+    }
+}
+
+// Takes word order and "pi" grouping into account.
+// A different head costs head_weight, and each modifier or "pi" group word
+// in one compound but not the other costs modifier_weight or pi_weight.
+// So "tomo tawa" (car) is close to "tomo" but far from "tawa tomo".
+pub struct Structured {
+    pub head_weight: f64,
+    pub modifier_weight: f64,
+    pub pi_weight: f64,
+}
+
+impl Default for Structured {
+    fn default() -> Self {
+        Structured {
+            head_weight: 3.0,
+            modifier_weight: 1.0,
+            pi_weight: 0.5,
+        }
+    }
+}
+
+impl Distance for Structured {
+    fn dist(&self, query: &CompoundWord, other: &CompoundWord) -> f64 {
+        let head = if (query.head() == other.head()) { 0.0 } else { self.head_weight };
+        let modifiers = query.modifiers().dist(other.modifiers()) as f64 * self.modifier_weight;
+
+        let flatten = |groups: &[WordSet]| groups.iter().fold(WordSet::new(), |acc, g| acc.union(g));
+        let pi = flatten(query.pi_groups()).dist(&flatten(other.pi_groups())) as f64 * self.pi_weight;
+
+        head + modifiers + pi
+    }
+}
+
+// Choice of distance, SymmetricDifference ignores word order and grouping, Structured doesn't.
+// Only ranks the candidates, which are always found within SearchOptions::max_dist by symmetric difference,
+// so a metric can reorder similar words but never reach one further away than that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    #[default]
+    SymmetricDifference,
+    Jaccard,
+    Containment,
+    Specificity,
+    Structured,
+}
+
+impl FromStr for Metric {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "symmetric_difference" | "sym" => Ok(Metric::SymmetricDifference),
+            "jaccard" => Ok(Metric::Jaccard),
+            "containment" => Ok(Metric::Containment),
+            "specificity" | "idf" => Ok(Metric::Specificity),
+            "structured" => Ok(Metric::Structured),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pu::Pu;
+    use crate::tests::entries;
+
+    #[test]
+    fn specificity_prefers_rare_words() {
+        let pu = Pu::from_subset(&[("jan", "person"), ("kala", "fish"), ("lili", "small")]);
+        let lines = [
+            "jan: [person 90, human 80, somebody 70]",
+            "jan lili: [child 80]",
+            "jan kala: [mermaid 30]",
+            "kala: [fish 90]",
+        ];
+
+        let entries = entries(&pu, &lines);
+
+        let specificity = Specificity::new(&entries);
+        let mermaid = &entries[4].toki_pona;
+        let person = &entries[0].toki_pona;
+        let fish = &entries[5].toki_pona;
+
+        // Both are one word away, but sharing "kala" is worth more than sharing "jan"
+        assert_eq!(SymmetricDifference.dist(mermaid, person), SymmetricDifference.dist(mermaid, fish));
+        assert!(specificity.dist(mermaid, fish) < specificity.dist(mermaid, person));
+    }
+}
@@ -0,0 +1,108 @@
+// Picking similar words that aren't all the same word.
+// The closest entries are often synonyms of a single compound, "ah, oh, ha, ooh, uh" for "a",
+// so we choose them one at a time by maximal marginal relevance, trading how close each
+// is to the query against how much it repeats what's already been chosen.
+
+use std::collections::HashMap;
+
+use crate::word_set::WordSet;
+use crate::{lemma, Candidate, TokiSama};
+
+// Candidates considered per result, the rest are too far down to be worth promoting
+const POOL_PER_RESULT: usize = 4;
+
+// Shortest stem that counts as the start of another word, "art" and "artist" aren't the same word
+const MIN_STEM_LEN: usize = 4;
+
+impl TokiSama {
+    // Chooses k of the sorted candidates, closest first.
+    // diversity runs from 0, keeping the closest in order, to 1, ignoring closeness after the first.
+    pub(crate) fn diversify<T>(&self, mut candidates: Vec<(Candidate, T)>, k: usize, diversity: f64) -> Vec<(Candidate, T)> {
+        if (diversity <= 0.0) {
+            candidates.truncate(k);
+            return candidates;
+        }
+
+        // No more than k from any one compound, or a common word like "a" fills the pool by itself
+        let mut per_compound: HashMap<WordSet, usize> = HashMap::new();
+        candidates.retain(|(candidate, _)| {
+            let count = per_compound.entry(self.dictionary.entries[candidate.entry_rank].toki_pona.words).or_insert(0);
+            *count += 1;
+            *count <= k
+        });
+        candidates.truncate(k * POOL_PER_RESULT);
+
+        // Closeness by position rather than score, so it means the same under any metric
+        let pool_len = candidates.len() as f64;
+        let mut pool: Vec<(f64, (Candidate, T))> = candidates.into_iter()
+            .enumerate()
+            .map(|(i, c)| (1.0 - i as f64 / pool_len, c))
+            .collect();
+
+        let mut chosen: Vec<(Candidate, T)> = Vec::with_capacity(k);
+        while (chosen.len() < k && !pool.is_empty()) {
+            let mut best = 0;
+            let mut best_value = f64::NEG_INFINITY;
+
+            for (i, (relevance, (candidate, _))) in pool.iter().enumerate() {
+                let redundancy = if (chosen.iter().any(|(x, _)| self.repeats(x, candidate))) { 1.0 } else { 0.0 };
+
+                let value = (1.0 - diversity) * relevance - diversity * redundancy;
+                if (value > best_value) {
+                    best = i;
+                    best_value = value;
+                }
+            }
+
+            chosen.push(pool.remove(best).1);
+        }
+
+        chosen
+    }
+
+    // Sharing a compound or an English stem, "teach" and "teaching", is as good as repeating a word.
+    // Compounds that only overlap are still worth showing, "toki musi" next to "toki pona".
+    fn repeats(&self, x: &Candidate, y: &Candidate) -> bool {
+        let x = &self.dictionary.entries[x.entry_rank];
+        let y = &self.dictionary.entries[y.entry_rank];
+
+        let x_stem = lemma::lemmatize(&x.english);
+        let y_stem = lemma::lemmatize(&y.english);
+        let shared_stem = x_stem.len().min(y_stem.len()) >= MIN_STEM_LEN && (x_stem.starts_with(&y_stem) || y_stem.starts_with(&x_stem));
+
+        x_stem == y_stem || shared_stem || x.toki_pona.words == y.toki_pona.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::SearchOptions;
+    use crate::pu::Pu;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn diverse_similar_words() {
+        let pu = Pu::from_subset(&[("a", "ah"), ("pona", "good"), ("mute", "many")]);
+        let lines = [
+            "a: [wow 95, ah 90, oh 85, ha 80, ooh 75]",
+            "a pona: [hooray 40]",
+            "a mute: [haha 30]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let similar = |options: &SearchOptions| toki_sama.lookup_with_options("wow", options, &pu)[0].similar
+            .iter()
+            .map(|s| s.english.clone())
+            .collect::<Vec<_>>();
+
+        let closest = SearchOptions { completions: 1, similar: 3, diversity: 0.0, ..SearchOptions::default() };
+        assert_eq!(vec!["ah", "oh", "ha"], similar(&closest));
+
+        // The closest stays first, then other compounds win over more synonyms of "a"
+        let varied = SearchOptions { diversity: 0.5, ..closest };
+        let varied = similar(&varied);
+        assert_eq!("ah", varied[0]);
+        assert!(varied.contains(&"hooray".to_owned()) && varied.contains(&"haha".to_owned()));
+        assert!(!varied.contains(&"wow".to_owned()));
+    }
+}
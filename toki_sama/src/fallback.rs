@@ -0,0 +1,171 @@
+// Proposing a translation for English words the dictionary doesn't have.
+//
+// With a corpus model we take the toki pona words most associated with the English word
+// in translated sentences, the same way the generated translations were mined.
+// Without one we can still translate the parts of "ice-cream" or "swimming pool" and combine them.
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::{Pu, TokiPonaWord};
+use crate::translate::FUNCTION_WORDS;
+use crate::{strongest_words, CompoundWord, Completion, TokiSama, TranslationSource};
+
+// Implemented by ilo_sona's corpus Analysis.
+pub trait Associations {
+    // toki pona words seen in translations of sentences containing the English word, strongest first
+    fn associations(&self, english: &str) -> Vec<(TokiPonaWord, f64)>;
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Provenance {
+    // Built from corpus association weights
+    Corpus { associations: Vec<WordAssociation> },
+    // The union of the translations of each part of the input
+    Parts { parts: Vec<PartTranslation> },
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordAssociation {
+    toki_pona: String,
+    weight: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartTranslation {
+    english: String,
+    toki_pona_string: String,
+}
+
+impl TokiSama {
+    // A compound for an English word that isn't in the dictionary, and where it came from.
+    // None if neither the corpus nor the parts of the input give anything.
+    pub fn propose(&self, english: &str, associations: Option<&dyn Associations>, options: &SearchOptions, pu: &Pu) -> Option<(CompoundWord, Provenance)> {
+        if let Some(associations) = associations {
+            let weighted = associations.associations(english);
+            let words = strongest_words(&weighted);
+
+            if (!words.is_empty()) {
+                let associations = weighted.iter().take(words.len())
+                    .map(|(tp, weight)| WordAssociation { toki_pona: pu.get(tp).to_owned(), weight: *weight })
+                    .collect();
+
+                return Some((CompoundWord::new(words), Provenance::Corpus { associations }));
+            }
+        }
+
+        let mut compound: Option<CompoundWord> = None;
+        let mut parts = Vec::new();
+
+        for part in english.split(|c: char| c.is_whitespace() || c == '-').filter(|x| !x.is_empty()) {
+            if (FUNCTION_WORDS.contains(&part.to_lowercase().as_str())) {
+                continue;
+            }
+
+            let best = self.exact_entries(part)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|i| options.allows(&self.dictionary.entries[*i]));
+
+            if let Some(entry_rank) = best {
+                let toki_pona = &self.dictionary.entries[entry_rank].toki_pona;
+                parts.push(PartTranslation {
+                    english: part.to_owned(),
+                    toki_pona_string: toki_pona.to_string(pu),
+                });
+
+                compound = Some(match compound {
+                    Some(c) => c.union(toki_pona),
+                    None => toki_pona.clone(),
+                });
+            }
+        }
+
+        compound.map(|c| (c, Provenance::Parts { parts }))
+    }
+
+    // Like lookup_with_options, but when nothing matches the input directly
+    // a proposed compound is searched around instead, still at most options.completions results.
+    pub fn lookup_with_fallback(&self, prefix: &str, options: &SearchOptions, associations: Option<&dyn Associations>, pu: &Pu) -> Vec<Completion> {
+        let mut completions = self.lookup_with_options(prefix, options, pu);
+
+        // Typo corrections might still be what was meant, so keep them and add the proposal after
+        if (completions.iter().any(|c| c.correction.is_none())) {
+            return completions;
+        }
+
+        if (options.completions == 0) {
+            return completions;
+        }
+
+        if let Some((toki_pona, provenance)) = self.propose(prefix, associations, options, pu) {
+            // Make room by dropping the least likely correction
+            completions.truncate(options.completions - 1);
+            completions.push(Completion {
+                english_search: prefix.to_owned(),
+                entry_english: prefix.to_owned(),
+                entry_weight: 0,
+                original_translation_string: toki_pona.to_string(pu),
+                breakdown: toki_pona.glosses(pu),
+                source: TranslationSource::Synthesized,
+                alternatives: Vec::new(),
+                similar: self.find_similar(&toki_pona, options, pu),
+                correction: None,
+                lemma: None,
+                provenance: Some(provenance),
+            });
+        }
+
+        completions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn propose_unknown_words() {
+        let pu = Pu::from_subset(&[("telo", "water"), ("suli", "big"), ("soweli", "animal")]);
+        let lines = [
+            "telo suli: [sea 80]",
+            "soweli: [lion 60]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let options = SearchOptions::default();
+
+        let (compound, provenance) = toki_sama.propose("sea-lion", None, &options, &pu).unwrap();
+        assert_eq!("telo suli soweli", compound.to_string(&pu));
+        assert!(matches!(provenance, Provenance::Parts { .. }));
+        assert!(toki_sama.propose("zzz", None, &options, &pu).is_none());
+
+        struct Corpus(Vec<(TokiPonaWord, f64)>);
+        impl Associations for Corpus {
+            fn associations(&self, _english: &str) -> Vec<(TokiPonaWord, f64)> {
+                self.0.clone()
+            }
+        }
+
+        // Weights that drop off sharply end the compound
+        let corpus = Corpus(vec![(pu.lookup("soweli").unwrap(), 500.0), (pu.lookup("telo").unwrap(), 300.0), (pu.lookup("suli").unwrap(), 100.0)]);
+        let (compound, provenance) = toki_sama.propose("seal", Some(&corpus), &options, &pu).unwrap();
+        assert_eq!("soweli telo", compound.to_string(&pu));
+        assert!(matches!(provenance, Provenance::Corpus { .. }));
+
+        let completions = toki_sama.lookup_with_fallback("walrus", &options, Some(&corpus), &pu);
+        assert_eq!(1, completions.len());
+        assert_eq!(TranslationSource::Synthesized, completions[0].source);
+
+        // The proposal comes after typo corrections, replacing the last if there's no room
+        let sources = |options: &SearchOptions| toki_sama.lookup_with_fallback("lian", options, Some(&corpus), &pu)
+            .into_iter()
+            .map(|c| c.source)
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![TranslationSource::Compounds, TranslationSource::Synthesized], sources(&SearchOptions { completions: 2, ..SearchOptions::default() }));
+        assert_eq!(vec![TranslationSource::Synthesized], sources(&SearchOptions { completions: 1, ..SearchOptions::default() }));
+    }
+}
@@ -0,0 +1,183 @@
+// Interlinear glossing of toki pona text, each word or known compound with its meaning underneath.
+//
+//   jan pi kama sona   li   lukin e   lipu   .
+//   student            [li] look  [e] paper  .
+
+use serde::Serialize;
+
+use crate::pu::{Pu, TokiPonaWord};
+use crate::{CompoundWord, TokiSama, TranslationSource};
+
+// The grammatical particles, with what they do in a sentence
+const PARTICLES: [(&str, &str); 5] = [
+    ("li", "separates the subject from the verb"),
+    ("e", "marks the direct object"),
+    ("la", "ends a context phrase"),
+    ("pi", "groups the following modifiers"),
+    ("o", "marks a command or someone addressed"),
+];
+
+// Longest run of words tried as a dictionary compound, counting any "pi"
+const MAX_COMPOUND_LEN: usize = 6;
+
+// English translations shown for a compound
+const COMPOUND_TRANSLATIONS: usize = 3;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GlossToken {
+    Particle { toki_pona: String, role: String },
+    // A run of words that is a known compound, eg "jan pi kama sona"
+    Compound { toki_pona: String, english: Vec<String> },
+    Word { toki_pona: String, definition: String },
+    // Not in pu, names are flagged separately as they're expected, eg "ma Kanata"
+    Unknown { text: String, name: bool },
+    Punctuation { text: String },
+}
+
+enum Token<'a> {
+    Word(&'a str, Option<TokiPonaWord>),
+    Punctuation(&'a str),
+}
+
+fn is_punctuation(c: char) -> bool {
+    !c.is_alphanumeric() && c != '-' && c != '\''
+}
+
+fn tokenize<'a>(sentence: &'a str, pu: &Pu) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+
+    for chunk in sentence.split_whitespace() {
+        let word_start = chunk.find(|c| !is_punctuation(c)).unwrap_or(chunk.len());
+        let word_end = chunk.char_indices().rev().find(|(_, c)| !is_punctuation(*c)).map(|(i, c)| i + c.len_utf8()).unwrap_or(word_start);
+
+        if (word_start > 0) {
+            tokens.push(Token::Punctuation(&chunk[..word_start]));
+        }
+
+        if (word_start < word_end) {
+            // Capitalised at the start of a sentence is still the word, only names aren't in pu
+            let word = &chunk[word_start..word_end];
+            let tp = pu.lookup(word).or_else(|| pu.lookup(&word.to_lowercase()));
+            tokens.push(Token::Word(word, tp));
+        }
+
+        if (word_end < chunk.len()) {
+            tokens.push(Token::Punctuation(&chunk[word_end..]));
+        }
+    }
+
+    tokens
+}
+
+fn particle_role(word: &str) -> Option<&'static str> {
+    PARTICLES.iter().find(|(particle, _)| particle.eq_ignore_ascii_case(word)).map(|(_, role)| *role)
+}
+
+impl TokiSama {
+    pub fn gloss(&self, sentence: &str, pu: &Pu) -> Vec<GlossToken> {
+        let tokens = tokenize(sentence, pu);
+        let mut glossed = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+
+        while (i < tokens.len()) {
+            if let Some((len, english)) = self.longest_compound(&tokens[i..], pu) {
+                let words: Vec<&str> = tokens[i..i + len].iter().filter_map(|t| match t {
+                    Token::Word(word, _) => Some(*word),
+                    Token::Punctuation(_) => None,
+                }).collect();
+
+                glossed.push(GlossToken::Compound { toki_pona: words.join(" "), english });
+                i += len;
+                continue;
+            }
+
+            glossed.push(match &tokens[i] {
+                Token::Punctuation(text) => GlossToken::Punctuation { text: text.to_string() },
+                Token::Word(word, Some(tp)) => match particle_role(word) {
+                    Some(role) => GlossToken::Particle { toki_pona: word.to_string(), role: role.to_owned() },
+                    None => GlossToken::Word { toki_pona: word.to_string(), definition: pu.define(tp).to_owned() },
+                },
+                Token::Word(word, None) => GlossToken::Unknown {
+                    text: word.to_string(),
+                    name: word.starts_with(char::is_uppercase),
+                },
+            });
+
+            i += 1;
+        }
+
+        glossed
+    }
+
+    // The most words at the start of tokens that are exactly a hand made dictionary compound,
+    // with its best English translations.
+    fn longest_compound(&self, tokens: &[Token], pu: &Pu) -> Option<(usize, Vec<String>)> {
+        // Only known words that can be inside a compound, "pi" is fine as long as it isn't first
+        let run: Vec<TokiPonaWord> = tokens.iter()
+            .take(MAX_COMPOUND_LEN)
+            .enumerate()
+            .map_while(|(i, t)| match t {
+                Token::Word(word, Some(tp)) if particle_role(word).is_none() || (word.eq_ignore_ascii_case("pi") && i > 0) => Some(*tp),
+                _ => None,
+            })
+            .collect();
+
+        for len in (2..=run.len()).rev() {
+            let compound = CompoundWord::with_structure(run[..len].iter().cloned().collect(), pu.lookup("pi"));
+
+            let english: Vec<String> = self.bk_tree.find_nodes_within(&compound, 0)
+                .iter()
+                .flat_map(|(node, _)| self.bk_tree.node_entries(*node))
+                .map(|i| &self.dictionary.entries[*i])
+                // Generated translations are unordered bags of words, so only trust hand made ones here
+                .filter(|e| e.source != TranslationSource::Generated && e.toki_pona.toki_pona == compound.toki_pona)
+                .take(COMPOUND_TRANSLATIONS)
+                .map(|e| e.english.clone())
+                .collect();
+
+            if (!english.is_empty()) {
+                return Some((len, english));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn interlinear_gloss() {
+        let pu = Pu::from_subset(&[("jan", "person"), ("pi", "of"), ("kama", "coming"), ("sona", "knowledge"), ("li", "predicate"), ("pona", "good"), ("mi", "I")]);
+        let lines = [
+            "jan pi kama sona: [student 80]",
+            "kama sona: [learn 90]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let gloss = |sentence: &str| -> Vec<String> {
+            toki_sama.gloss(sentence, &pu)
+                .iter()
+                .map(|t| match t {
+                    GlossToken::Particle { toki_pona, .. } => format!("[{}]", toki_pona),
+                    GlossToken::Compound { english, .. } => english.join("/"),
+                    GlossToken::Word { definition, .. } => definition.clone(),
+                    GlossToken::Unknown { text, name } => format!("{}?{}", text, name),
+                    GlossToken::Punctuation { text } => text.clone(),
+                })
+                .collect()
+        };
+
+        assert_eq!(vec!["student", "[li]", "good", ",", "person", "Sonja?true", "[li]", "learn", "!"], gloss("jan pi kama sona li pona, jan Sonja li kama sona!"));
+
+        // Capitals starting a sentence aren't names
+        assert_eq!(vec!["person", "[li]", "good", ".", "I", "good", "."], gloss("Jan li pona. Mi pona."));
+
+        // Names can end in letters wider than a byte
+        assert_eq!(vec!["person", "Sonjá?true", "[li]", "good", "."], gloss("jan Sonjá li pona."));
+    }
+}
@@ -0,0 +1,177 @@
+// Word ladders, a chain of dictionary entries from one English word to another
+// where each hop only changes the toki pona translation a little.
+//
+//   poetry (toki musi) -> game (musi) -> sport (musi utala)
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::Pu;
+use crate::{TokiSama, TranslationSource};
+
+#[derive(Debug, Serialize)]
+pub struct LadderStep {
+    english: String,
+    toki_pona_string: String,
+    source: TranslationSource,
+    // Set distance from the previous step
+    dist: u32,
+}
+
+struct Visit {
+    prev: Option<usize>,
+    entry_rank: usize,
+    // Set distance from prev
+    dist: u32,
+}
+
+impl TokiSama {
+    // The shortest chain of entries from one English word to another, with each
+    // step at most max_step apart. None if either word is unknown, has no entries
+    // the options allow, or they aren't connected.
+    //
+    // The graph is implicit, nodes are the distinct compounds in the BK-tree and
+    // edges are found by searching the tree around each node as we visit it.
+    pub fn ladder(&self, from: &str, to: &str, max_step: u32, options: &SearchOptions, pu: &Pu) -> Option<Vec<LadderStep>> {
+        let allowed = |english: &str| -> Option<Vec<usize>> {
+            let entries: Vec<usize> = self.exact_entries(english)?
+                .into_iter()
+                .filter(|i| options.allows(&self.dictionary.entries[*i]))
+                .collect();
+
+            if (entries.is_empty()) { None } else { Some(entries) }
+        };
+
+        let from_entries = allowed(from)?;
+        let to_entries = allowed(to)?;
+
+        // The same word, or inflections of it, is already there
+        if (from_entries == to_entries) {
+            return Some(vec![self.ladder_step(from_entries[0], 0, pu)]);
+        }
+
+        let node_of = |entry_rank: usize| {
+            let toki_pona = &self.dictionary.entries[entry_rank].toki_pona;
+            self.bk_tree.find_nodes_within(toki_pona, 0)[0].0
+        };
+
+        // Best allowed entry of a node, skipping the endpoints so the middle of the ladder says something new
+        let representative = |node: usize| {
+            self.bk_tree.node_entries(node).iter().cloned()
+                .filter(|i| options.allows(&self.dictionary.entries[*i]))
+                .find(|i| !from_entries.contains(i) && !to_entries.contains(i))
+        };
+
+        let goals: HashMap<usize, usize> = to_entries.iter().rev().map(|i| (node_of(*i), *i)).collect();
+
+        let mut visited: HashMap<usize, Visit> = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for entry_rank in &from_entries {
+            let node = node_of(*entry_rank);
+            if let Some(goal_entry) = goals.get(&node) {
+                // Translated the same way, eg "poetry" and "joke"
+                let steps = vec![
+                    self.ladder_step(*entry_rank, 0, pu),
+                    self.ladder_step(*goal_entry, 0, pu),
+                ];
+
+                return Some(steps);
+            }
+
+            visited.entry(node).or_insert_with(|| {
+                queue.push_back(node);
+                Visit { prev: None, entry_rank: *entry_rank, dist: 0 }
+            });
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let word = self.bk_tree.node_word(node);
+            let mut neighbours = self.bk_tree.find_nodes_within(word, max_step);
+
+            // Visit closer compounds first so ties prefer smaller steps
+            neighbours.sort_by_key(|(next, dist)| (*dist, *next));
+
+            for (next, dist) in neighbours {
+                if (visited.contains_key(&next)) {
+                    continue;
+                }
+
+                if let Some(goal_entry) = goals.get(&next) {
+                    visited.insert(next, Visit { prev: Some(node), entry_rank: *goal_entry, dist });
+                    return Some(self.build_ladder(next, &visited, pu));
+                }
+
+                if let Some(entry_rank) = representative(next) {
+                    visited.insert(next, Visit { prev: Some(node), entry_rank, dist });
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn ladder_step(&self, entry_rank: usize, dist: u32, pu: &Pu) -> LadderStep {
+        let entry = &self.dictionary.entries[entry_rank];
+        LadderStep {
+            english: entry.english.clone(),
+            toki_pona_string: entry.toki_pona.to_string(pu),
+            source: entry.source,
+            dist,
+        }
+    }
+
+    fn build_ladder(&self, end: usize, visited: &HashMap<usize, Visit>, pu: &Pu) -> Vec<LadderStep> {
+        let mut steps = Vec::new();
+        let mut cur = Some(end);
+
+        while let Some(node) = cur {
+            let visit = &visited[&node];
+            steps.push(self.ladder_step(visit.entry_rank, visit.dist, pu));
+            cur = visit.prev;
+        }
+
+        steps.reverse();
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::SearchOptions;
+    use crate::pu::Pu;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn ladder_between_words() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("utala", "fight"), ("kala", "fish")]);
+        let toki_sama = toki_sama(&pu, &[
+            "toki musi: [poetry 80]",
+            "musi: [game 70]",
+            "musi utala: [sport 60]",
+            "kala: [fish 50]",
+        ]);
+
+        let options = SearchOptions::default();
+        let english = |from: &str, to: &str, max_step: u32, options: &SearchOptions| -> Option<Vec<String>> {
+            toki_sama.ladder(from, to, max_step, options, &pu).map(|steps| steps.into_iter().map(|s| s.english).collect())
+        };
+
+        assert_eq!(Some(vec!["poetry".to_owned(), "game".to_owned(), "sport".to_owned()]), english("poetry", "sport", 1, &options));
+
+        // Bigger steps can skip the middle
+        assert_eq!(Some(vec!["poetry".to_owned(), "sport".to_owned()]), english("poetry", "sport", 2, &options));
+
+        assert_eq!(Some(vec!["poetry".to_owned()]), english("poetry", "Poetry", 1, &options));
+        assert_eq!(None, english("poetry", "fish", 1, &options));
+        assert_eq!(None, english("poetry", "zebra", 3, &options));
+
+        // Filtered out endpoints can't start or end a ladder
+        let options = SearchOptions { min_weight: 65, ..SearchOptions::default() };
+        assert_eq!(None, english("poetry", "sport", 2, &options));
+        assert_eq!(None, english("sport", "poetry", 2, &options));
+    }
+}
@@ -0,0 +1,207 @@
+// A small rule based English lemmatizer.
+// It only needs to be consistent, inflections of a word should land on the same lemma
+// so "hunted", "hunting" and "hunts" all find "hunt". It doesn't try to handle
+// derivation, "teacher" stays "teacher".
+
+const IRREGULAR: [(&str, &str); 58] = [
+    ("children", "child"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("mice", "mouse"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("geese", "goose"),
+    ("wolves", "wolf"),
+    ("knives", "knife"),
+    ("leaves", "leaf"),
+    ("lives", "life"),
+    ("wives", "wife"),
+    ("halves", "half"),
+    ("is", "be"),
+    ("are", "be"),
+    ("was", "be"),
+    ("were", "be"),
+    ("been", "be"),
+    ("being", "be"),
+    ("has", "have"),
+    ("had", "have"),
+    ("does", "do"),
+    ("did", "do"),
+    ("done", "do"),
+    ("went", "go"),
+    ("gone", "go"),
+    ("goes", "go"),
+    ("ran", "run"),
+    ("saw", "see"),
+    ("seen", "see"),
+    ("ate", "eat"),
+    ("eaten", "eat"),
+    ("made", "make"),
+    ("said", "say"),
+    ("took", "take"),
+    ("taken", "take"),
+    ("came", "come"),
+    ("gave", "give"),
+    ("given", "give"),
+    ("knew", "know"),
+    ("known", "know"),
+    ("thought", "think"),
+    ("bought", "buy"),
+    ("brought", "bring"),
+    ("taught", "teach"),
+    ("caught", "catch"),
+    ("fought", "fight"),
+    ("found", "find"),
+    ("told", "tell"),
+    ("sold", "sell"),
+    ("felt", "feel"),
+    ("kept", "keep"),
+    ("slept", "sleep"),
+    ("wrote", "write"),
+    ("written", "write"),
+    ("spoke", "speak"),
+    ("spoken", "speak"),
+    ("used", "use"),
+];
+
+fn is_vowel(c: u8) -> bool {
+    matches!(c, b'a' | b'e' | b'i' | b'o' | b'u')
+}
+
+fn has_vowel(s: &str) -> bool {
+    s.bytes().any(|c| is_vowel(c) || c == b'y')
+}
+
+// "runn" -> "run", but not "fall" or "miss"
+fn undouble(stem: &str) -> Option<&str> {
+    let bytes = stem.as_bytes();
+    let n = bytes.len();
+    if (n >= 3 && bytes[n - 1] == bytes[n - 2] && !is_vowel(bytes[n - 1]) && !matches!(bytes[n - 1], b'l' | b's' | b'z' | b'f')) {
+        Some(&stem[..n - 1])
+    }
+    else {
+        None
+    }
+}
+
+// Short consonant-vowel-consonant stems usually lost an 'e', "mak" -> "make"
+fn restore_e(stem: &str) -> String {
+    let bytes = stem.as_bytes();
+    let n = bytes.len();
+    let vowel_groups = bytes.windows(2).filter(|w| !is_vowel(w[0]) && is_vowel(w[1])).count()
+        + if (n > 0 && is_vowel(bytes[0])) { 1 } else { 0 };
+
+    let cvc = n >= 3
+        && !is_vowel(bytes[n - 3])
+        && is_vowel(bytes[n - 2])
+        && !is_vowel(bytes[n - 1])
+        && !matches!(bytes[n - 1], b'w' | b'x' | b'y');
+
+    if (cvc && vowel_groups == 1) {
+        format!("{}e", stem)
+    }
+    else {
+        stem.to_owned()
+    }
+}
+
+fn strip_plural(word: &str) -> String {
+    if (word.len() <= 3 || !word.ends_with('s')) {
+        return word.to_owned();
+    }
+
+    if (word.ends_with("ss") || word.ends_with("us") || word.ends_with("is")) {
+        return word.to_owned();
+    }
+
+    if let Some(stem) = word.strip_suffix("ies") {
+        if (stem.len() >= 2) {
+            return format!("{}y", stem);
+        }
+    }
+
+    if let Some(stem) = word.strip_suffix("es") {
+        if (["sh", "ch", "x", "z", "ss"].iter().any(|s| stem.ends_with(s))) {
+            return stem.to_owned();
+        }
+    }
+
+    word[..word.len() - 1].to_owned()
+}
+
+fn strip_verb_suffix(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ing") {
+        if (stem.len() >= 2 && has_vowel(stem)) {
+            return match undouble(stem) {
+                Some(undoubled) => undoubled.to_owned(),
+                None => restore_e(stem),
+            };
+        }
+    }
+
+    if (word.len() > 4 && !word.ends_with("eed")) {
+        if let Some(stem) = word.strip_suffix("ied") {
+            return format!("{}y", stem);
+        }
+
+        if let Some(stem) = word.strip_suffix("ed") {
+            if (has_vowel(stem)) {
+                return match undouble(stem) {
+                    Some(undoubled) => undoubled.to_owned(),
+                    None => restore_e(stem),
+                };
+            }
+        }
+    }
+
+    word.to_owned()
+}
+
+fn lemmatize_word(word: &str) -> String {
+    if let Some((_, lemma)) = IRREGULAR.iter().find(|(x, _)| *x == word) {
+        return lemma.to_string();
+    }
+
+    // Only plain lowercase words, leave anything like "uh-huh" or "(n)" alone
+    if (!word.bytes().all(|c| c.is_ascii_lowercase())) {
+        return word.to_owned();
+    }
+
+    strip_verb_suffix(&strip_plural(word))
+}
+
+// Lemmatize each word of a possibly multi-word English string.
+pub fn lemmatize(english: &str) -> String {
+    let lowercase = english.to_lowercase();
+    let words: Vec<String> = lowercase.split_whitespace().map(lemmatize_word).collect();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lemmatize_inflections() {
+        for (word, expected) in &[
+            ("hunted", "hunt"),
+            ("hunting", "hunt"),
+            ("hunts", "hunt"),
+            ("teachers", "teacher"),
+            ("landings", "land"),
+            ("making", "make"),
+            ("stopped", "stop"),
+            ("falling", "fall"),
+            ("studies", "study"),
+            ("houses", "house"),
+            ("churches", "church"),
+            ("children", "child"),
+            ("glass", "glass"),
+            ("thing", "thing"),
+            ("need", "need"),
+            ("uh-huh", "uh-huh"),
+        ] {
+            assert_eq!(*expected, lemmatize(word), "lemmatizing {}", word);
+        }
+    }
+}
@@ -1,38 +1,163 @@
 #![allow(unused_parens)]
 
 pub mod pu;
+pub mod bk_tree;
+pub mod word_set;
+pub mod query;
+pub mod distance;
+pub mod trie;
+pub mod lemma;
+pub mod options;
+pub mod ladder;
+pub mod region;
+pub mod antonym;
+pub mod analogy;
+pub mod interlinear;
+pub mod translate;
+pub mod fallback;
+pub mod workshop;
+pub mod popularity;
+pub mod diversity;
+
+use std::collections::{HashMap, HashSet};
 
-use std::collections::HashMap;
-
-use radix_trie::{Trie, TrieCommon};
 use std::str::FromStr;
-use serde::Serialize;
-
-use pu::{Pu, TokiPonaWord};
-
+use serde::{Serialize, Deserialize};
+
+use pu::{Pu, TokiPonaWord, PartOfSpeech, WordCategory};
+use bk_tree::BkTree;
+use word_set::WordSet;
+use query::{Query, QueryError};
+use distance::{Distance, Metric, Specificity};
+use trie::Trie;
+use options::SearchOptions;
+use popularity::{Frequencies, ScoreParts};
+
+// A toki pona phrase such as "tomo tawa" or "jan pi kama sona".
+// The first word is the head, the words after it modify it, and "pi"
+// starts a group of words that modifies as a unit.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CompoundWord {
     // Almost all words won't be longer than 4
+    // Kept in order for display, including any "pi"
     toki_pona: smallvec::SmallVec<[TokiPonaWord; 4]>,
+    // The content words as a set, for fast order-free comparisons
+    words: WordSet,
+    head: Option<TokiPonaWord>,
+    modifiers: WordSet,
+    pi_groups: smallvec::SmallVec<[WordSet; 1]>,
 }
 
 impl CompoundWord {
-    fn dist(&self, other: &Self) -> u32 {
-        let mut dist = self.toki_pona.len() + other.toki_pona.len();
+    // A compound with no "pi" grouping, the first word is the head.
+    pub fn new(toki_pona: smallvec::SmallVec<[TokiPonaWord; 4]>) -> Self {
+        Self::with_structure(toki_pona, None)
+    }
+
+    fn with_structure(toki_pona: smallvec::SmallVec<[TokiPonaWord; 4]>, pi: Option<TokiPonaWord>) -> Self {
+        let mut words = WordSet::new();
+        let mut head = None;
+        let mut modifiers = WordSet::new();
+        let mut pi_groups: smallvec::SmallVec<[WordSet; 1]> = smallvec::SmallVec::new();
+
+        for tp in &toki_pona {
+            // "pi" on its own is just the word, it only groups after a head
+            if (Some(*tp) == pi && head.is_some()) {
+                pi_groups.push(WordSet::new());
+                continue;
+            }
+
+            words.insert(*tp);
+
+            if let Some(group) = pi_groups.last_mut() {
+                group.insert(*tp);
+            }
+            else if (head.is_none()) {
+                head = Some(*tp);
+            }
+            else {
+                modifiers.insert(*tp);
+            }
+        }
 
-        for y in &other.toki_pona {
-            if (self.toki_pona.contains(y)) {
-                dist -= 2;
+        CompoundWord {
+            toki_pona,
+            words,
+            head,
+            modifiers,
+            pi_groups,
+        }
+    }
+
+    // Parse a space separated toki pona phrase, eg "tomo tawa"
+    // Returns the first unknown word on failure.
+    pub fn parse<'a>(toki_pona: &'a str, pu: &Pu) -> Result<Self, &'a str> {
+        let mut compound_word_parts = smallvec::SmallVec::new();
+        for word in toki_pona.split_whitespace() {
+            match (pu.lookup(word)) {
+                Some(w) => compound_word_parts.push(w),
+                _ => {
+                    return Err(word);
+                }
             }
         }
 
-        dist as u32
+        Ok(Self::with_structure(compound_word_parts, pu.lookup("pi")))
+    }
+
+    pub(crate) fn dist(&self, other: &Self) -> u32 {
+        self.words.dist(&other.words)
     }
 
     pub fn len(&self) -> usize {
         self.toki_pona.len()
     }
 
+    pub fn words(&self) -> &WordSet {
+        &self.words
+    }
+
+    pub fn head(&self) -> Option<TokiPonaWord> {
+        self.head
+    }
+
+    // Words directly modifying the head, not including any "pi" groups
+    pub fn modifiers(&self) -> &WordSet {
+        &self.modifiers
+    }
+
+    pub fn pi_groups(&self) -> &[WordSet] {
+        &self.pi_groups
+    }
+
+    // Words in order, skipping "pi"
+    fn content_words(&self) -> impl Iterator<Item = TokiPonaWord> + '_ {
+        self.toki_pona.iter().cloned().filter(move |tp| self.words.contains(*tp))
+    }
+
+    // Set operations flatten any structure.
+    // They keep the word order of self, with any new words from other appended.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut toki_pona: smallvec::SmallVec<[TokiPonaWord; 4]> = self.content_words().collect();
+        let mut seen = self.words;
+        for tp in other.content_words() {
+            if (!seen.contains(tp)) {
+                seen.insert(tp);
+                toki_pona.push(tp);
+            }
+        }
+
+        Self::new(toki_pona)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::new(self.content_words().filter(|tp| other.words.contains(*tp)).collect())
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self::new(self.content_words().filter(|tp| !other.words.contains(*tp)).collect())
+    }
+
     fn to_string(&self, pu: &Pu) -> String {
         let mut word = String::new();
         for tp in &self.toki_pona {
@@ -45,13 +170,25 @@ impl CompoundWord {
 
         word
     }
+
+    // Pu for a compound of only pu words, otherwise the least established word's category
+    pub fn category(&self, pu: &Pu) -> WordCategory {
+        self.words.iter().map(|tp| pu.category(&tp)).max().unwrap_or(WordCategory::Pu)
+    }
+
+    // Every word in order, including any "pi", with its part of speech and gloss
+    pub fn glosses(&self, pu: &Pu) -> Vec<WordGloss> {
+        self.toki_pona.iter().map(|tp| WordGloss::new(*tp, pu)).collect()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum TranslationSource {
     NimiPu,
     Compounds,
     Generated,
+    // Built at query time rather than read from a dictionary
+    Synthesized,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -59,7 +196,9 @@ pub struct Translation {
     weight: u32,
     toki_pona: CompoundWord,
     english: String,
-    source : TranslationSource
+    source : TranslationSource,
+    // The least established category of any word in the compound
+    category: WordCategory,
 }
 
 impl Translation {
@@ -70,20 +209,12 @@ impl Translation {
 
         let (toki_pona, english_definitions_array) = line.split_once(":")?;
 
-        let toki_pona_words: Vec<&str> = toki_pona.split_whitespace().collect();
-        let mut compound_word_parts = smallvec::SmallVec::new();
-        for word in toki_pona_words {
-            match (pu.lookup(word)) {
-                Some(w) => compound_word_parts.push(w),
-                _ => {
-                    println!("Unknown toki pona word {}", word);
-                    return None;
-                }
+        let compound_word = match CompoundWord::parse(toki_pona, pu) {
+            Ok(compound_word) => compound_word,
+            Err(word) => {
+                println!("Unknown toki pona word {}", word);
+                return None;
             }
-        }
-
-        let compound_word = CompoundWord {
-            toki_pona: compound_word_parts,
         };
 
         let start = english_definitions_array.find('[')?;
@@ -91,6 +222,7 @@ impl Translation {
         let english_definitions: &str = &english_definitions_array[start + 1..end];
         let def_splits: Vec<&str> = english_definitions.split(',').collect();
 
+        let category = compound_word.category(pu);
         let mut defs = Vec::new();
 
         for def_split in def_splits {
@@ -102,6 +234,7 @@ impl Translation {
                 english: english.to_ascii_lowercase(),
                 toki_pona: compound_word.clone(),
                 source,
+                category,
             })
         }
 
@@ -122,33 +255,22 @@ impl Translation {
             weighted_toki_pona.push((toki_res, weight));
         }
 
-        // TODO improve this
-        // We want to use these weights to forma a compound word
-        // for now keep going down adding until weights drop off
-
         if weighted_toki_pona.is_empty() {
             return Some(translations);
         }
 
         let initial_weight = weighted_toki_pona[0].1;
-
-        let mut compound : smallvec::SmallVec<[TokiPonaWord; 4]> = smallvec::SmallVec::new();
-
-        for (tp, weight) in weighted_toki_pona {
-            if (weight as f64) > ((initial_weight as f64) / 2.25) { 
-                compound.push(tp);
-            }
-            else {
-                break;
-            }
-        }
+        let weighted: Vec<(TokiPonaWord, f64)> = weighted_toki_pona.iter().map(|(tp, weight)| (*tp, *weight as f64)).collect();
+        let compound = strongest_words(&weighted);
 
         // Filter some garbage
         if (compound.len() < 8) {
+            let toki_pona = CompoundWord::new(compound);
             translations.push(Translation {
                 weight : initial_weight / 10,
                 english : english.to_owned(),
-                toki_pona : CompoundWord { toki_pona : compound },
+                category : toki_pona.category(pu),
+                toki_pona,
                 source : TranslationSource::Generated,
             });
         }
@@ -157,6 +279,18 @@ impl Translation {
     }
 }
 
+// TODO improve this
+// We want to use association weights to form a compound word,
+// for now keep going down adding until weights drop off.
+// Expects the weights strongest first.
+pub(crate) fn strongest_words(weighted: &[(TokiPonaWord, f64)]) -> smallvec::SmallVec<[TokiPonaWord; 4]> {
+    let initial_weight = weighted.first().map(|(_, weight)| *weight).unwrap_or(0.0);
+    weighted.iter()
+        .take_while(|(_, weight)| *weight > initial_weight / 2.25)
+        .map(|(tp, _)| *tp)
+        .collect()
+}
+
 impl PartialOrd for Translation {
     fn partial_cmp(&self, other : &Self) -> Option<std::cmp::Ordering> {
         Some(self.source.cmp(&other.source)
@@ -218,11 +352,26 @@ impl Dictionary {
 pub struct TokiSama {
     dictionary: Dictionary,
     posting_lists: Vec<Vec<usize>>,
-    trie: Trie<String, usize>,
+    trie: Trie,
+    // Lemma to value ranks, so "hunted" can find "hunt" and "hunting"
+    lemmas: HashMap<String, Vec<usize>>,
+    bk_tree: BkTree,
+    specificity: Specificity,
+    structured: distance::Structured,
+    // Parts of the completion score of each entry, the best of each value's entries,
+    // and the best of every value below each trie node
+    entry_scores: Vec<ScoreParts>,
+    value_scores: Vec<ScoreParts>,
+    node_scores: Vec<ScoreParts>,
 }
 
 impl TokiSama {
-    pub fn new(mut dictionary: Dictionary) -> Self {
+    pub fn new(dictionary: Dictionary) -> Self {
+        Self::with_frequencies(dictionary, &Frequencies::default())
+    }
+
+    // Frequencies of English words let common words rank higher as completions.
+    pub fn with_frequencies(mut dictionary: Dictionary, frequencies: &Frequencies) -> Self {
         dictionary.entries.sort();
 
         let mut trie = Trie::new();
@@ -230,9 +379,9 @@ impl TokiSama {
 
         for entry_rank in 0..dictionary.entries.len() {
             let entry = &dictionary.entries[entry_rank];
-            let value_rank = trie.get(&entry.english).cloned().unwrap_or_else(|| {
+            let value_rank = trie.get(&entry.english).unwrap_or_else(|| {
                 let new_value_rank = posting_lists.len();
-                trie.insert(entry.english.clone(), new_value_rank);
+                trie.insert(&entry.english, new_value_rank);
                 posting_lists.push(Vec::with_capacity(1));
                 new_value_rank
             });
@@ -240,101 +389,437 @@ impl TokiSama {
             posting_lists.get_mut(value_rank).unwrap().push(entry_rank);
         }
 
+        let mut lemmas: HashMap<String, Vec<usize>> = HashMap::new();
+        for (value_rank, posting_list) in posting_lists.iter().enumerate() {
+            let english = &dictionary.entries[posting_list[0]].english;
+            lemmas.entry(lemma::lemmatize(english)).or_default().push(value_rank);
+        }
+
+        let mut bk_tree = BkTree::new();
+        for (entry_rank, entry) in dictionary.entries.iter().enumerate() {
+            bk_tree.insert(&entry.toki_pona, entry_rank);
+        }
+
+        let specificity = Specificity::new(&dictionary.entries);
+
+        let mut max_weights = [0; TranslationSource::Synthesized as usize + 1];
+        for entry in &dictionary.entries {
+            let max = &mut max_weights[entry.source as usize];
+            *max = entry.weight.max(*max);
+        }
+
+        let entry_scores: Vec<ScoreParts> = dictionary.entries.iter().map(|e| ScoreParts::new(e, &max_weights, frequencies)).collect();
+        let value_scores: Vec<ScoreParts> = posting_lists.iter()
+            .map(|posting_list| posting_list.iter().map(|i| entry_scores[*i]).fold(ScoreParts::default(), ScoreParts::max))
+            .collect();
+        let node_scores = trie.subtree_max(|v| value_scores[v], ScoreParts::max, ScoreParts::default());
+
         TokiSama {
             dictionary,
             posting_lists,
             trie,
+            lemmas,
+            bk_tree,
+            specificity,
+            structured: distance::Structured::default(),
+            entry_scores,
+            value_scores,
+            node_scores,
         }
     }
 
-    fn populate_completion(&self, search_string: &str, entry_rank: usize, pu: &Pu) -> Completion {
-        let entry = &self.dictionary.entries[entry_rank];
+    pub fn distance(&self, metric: Metric) -> &dyn Distance {
+        match metric {
+            Metric::SymmetricDifference => &distance::SymmetricDifference,
+            Metric::Jaccard => &distance::Jaccard,
+            Metric::Containment => &distance::Containment,
+            Metric::Specificity => &self.specificity,
+            Metric::Structured => &self.structured,
+        }
+    }
 
-        let mut similar = Vec::new();
+    // Entries near a compound, closest first.
+    // The BK-tree finds everything within a set distance of the query, the metric decides the order.
+    fn ranked_candidates(&self, toki_pona: &CompoundWord, exclude_rank: Option<usize>, options: &SearchOptions) -> Vec<Candidate> {
+        let max_dist = options.max_dist_for(toki_pona.len());
+        let distance = self.distance(options.metric);
+
+        let mut candidates: Vec<Candidate> = self.bk_tree.find_within(toki_pona, max_dist)
+            .into_iter()
+            .filter(|(i, _)| Some(*i) != exclude_rank && options.allows(&self.dictionary.entries[*i]))
+            .map(|(entry_rank, dist)| Candidate {
+                entry_rank,
+                dist,
+                score: distance.dist(toki_pona, &self.dictionary.entries[entry_rank].toki_pona),
+            })
+            .collect();
 
-        // DUMB impl
-        const MAX: usize = 5;
-        let max_dist = entry.toki_pona.len().max(1) as u32;
+        // Sort before building strings so we only stringify what we return.
+        // Tie break on entry rank to keep results stable across tree layouts.
+        candidates.sort_by(|x, y| self.candidate_order(x, y));
 
-        for i in 0..self.dictionary.entries.len() {
-            if (i == entry_rank) {
-                continue;
-            }
+        candidates
+    }
 
-            let e = &self.dictionary.entries[i];
-            let dist = e.toki_pona.dist(&entry.toki_pona);
-            if (dist <= max_dist) {
-                similar.push(ThesaurusResult {
-                    english: e.english.clone(),
-                    toki_pona_len : e.toki_pona.len() as u32,
-                    toki_pona_string: e.toki_pona.to_string(pu),
-                    source: e.source,
-                    dist,
-                });
+    fn candidate_order(&self, x: &Candidate, y: &Candidate) -> std::cmp::Ordering {
+        let x_len = self.dictionary.entries[x.entry_rank].toki_pona.len();
+        let y_len = self.dictionary.entries[y.entry_rank].toki_pona.len();
+        x.score.partial_cmp(&y.score).unwrap()
+            .then(x.dist.cmp(&y.dist))
+            .then(x_len.cmp(&y_len))
+            .then(x.entry_rank.cmp(&y.entry_rank))
+    }
+
+    fn thesaurus_result(&self, candidate: &Candidate, query: &CompoundWord, pu: &Pu) -> ThesaurusResult {
+        let e = &self.dictionary.entries[candidate.entry_rank];
+        let gloss_all = |words: CompoundWord| words.content_words().map(|tp| WordGloss::new(tp, pu)).collect();
+
+        ThesaurusResult {
+            english: e.english.clone(),
+            toki_pona_len : e.toki_pona.len() as u32,
+            toki_pona_string: e.toki_pona.to_string(pu),
+            breakdown: e.toki_pona.glosses(pu),
+            source: e.source,
+            dist: candidate.dist,
+            score: candidate.score,
+            shared: gloss_all(query.intersection(&e.toki_pona)),
+            only_query: gloss_all(query.difference(&e.toki_pona)),
+            only_result: gloss_all(e.toki_pona.difference(query)),
+        }
+    }
+
+    fn find_similar(&self, toki_pona: &CompoundWord, options: &SearchOptions, pu: &Pu) -> Vec<ThesaurusResult> {
+        self.find_similar_to_any(&[toki_pona], None, options, pu)
+    }
+
+    // The similar entries to any of the given compounds, the closest once per English word,
+    // picked for variety. Entries for the excluded English word are left out.
+    fn find_similar_to_any(&self, compounds: &[&CompoundWord], exclude_english: Option<&str>, options: &SearchOptions, pu: &Pu) -> Vec<ThesaurusResult> {
+        // (candidate, which compound it's near)
+        let mut closest: HashMap<&str, (Candidate, usize)> = HashMap::new();
+
+        for (i, toki_pona) in compounds.iter().enumerate() {
+            for candidate in self.ranked_candidates(toki_pona, None, options) {
+                let english = self.dictionary.entries[candidate.entry_rank].english.as_str();
+                if (exclude_english.is_some_and(|x| x.eq_ignore_ascii_case(english))) {
+                    continue;
+                }
+
+                // Keep whichever compound it's closest to
+                match closest.get(english) {
+                    Some((best, _)) if self.candidate_order(best, &candidate).is_le() => {}
+                    _ => { closest.insert(english, (candidate, i)); }
+                }
             }
         }
 
-        similar.sort_by(|x, y| {
-            x.dist.cmp(&y.dist)
-                .then(x.toki_pona_len.cmp(&y.toki_pona_len))
-        });
+        let mut closest: Vec<(Candidate, usize)> = closest.into_values().collect();
+        closest.sort_by(|(x, _), (y, _)| self.candidate_order(x, y));
 
-        similar = similar.into_iter().take(MAX).collect();
+        self.diversify(closest, options.similar, options.diversity)
+            .iter()
+            .map(|(candidate, i)| self.thesaurus_result(candidate, compounds[*i], pu))
+            .collect()
+    }
+
+    // A completion for an English word from all of its allowed entries, best ranked first.
+    fn populate_completion(&self, search_string: &str, entry_ranks: &[usize], options: &SearchOptions, pu: &Pu) -> Completion {
+        let entry = &self.dictionary.entries[entry_ranks[0]];
+        let compounds: Vec<&CompoundWord> = entry_ranks.iter().map(|i| &self.dictionary.entries[*i].toki_pona).collect();
+        let similar = self.find_similar_to_any(&compounds, Some(&entry.english), options, pu);
+
+        let alternatives = entry_ranks[1..].iter()
+            .map(|i| {
+                let alternative = &self.dictionary.entries[*i];
+                AlternativeTranslation {
+                    toki_pona_string: alternative.toki_pona.to_string(pu),
+                    breakdown: alternative.toki_pona.glosses(pu),
+                    weight: alternative.weight,
+                    source: alternative.source,
+                }
+            })
+            .collect();
 
         Completion {
             english_search: search_string.to_owned(),
             entry_english: entry.english.to_owned(),
             entry_weight : entry.weight,
             original_translation_string: entry.toki_pona.to_string(pu),
+            breakdown: entry.toki_pona.glosses(pu),
             source: entry.source,
+            alternatives,
             similar,
+            correction: None,
+            lemma: None,
+            provenance: None,
         }
     }
 
+    // Entries of an English word the options allow, best ranked first.
+    fn allowed_entries(&self, value_rank: usize, options: &SearchOptions) -> Vec<usize> {
+        self.posting_lists[value_rank].iter()
+            .cloned()
+            .filter(|i| options.allows(&self.dictionary.entries[*i]))
+            .collect()
+    }
+
+    // Every entry for an exact English word, falling back to entries with the same lemma.
+    fn exact_entries(&self, english: &str) -> Option<Vec<usize>> {
+        let normalized = english.to_lowercase();
+
+        if let Some(value_rank) = self.trie.get(&normalized) {
+            return Some(self.posting_lists[value_rank].clone());
+        }
+
+        let value_ranks = self.lemmas.get(&lemma::lemmatize(&normalized))?;
+        let mut entry_ranks: Vec<usize> = value_ranks.iter().flat_map(|v| self.posting_lists[*v].iter().cloned()).collect();
+        entry_ranks.sort();
+        Some(entry_ranks)
+    }
+
+    // The best ranked allowed entry for an exact English word or an inflection of it.
+    fn best_entry(&self, english: &str, options: &SearchOptions) -> Option<usize> {
+        self.exact_entries(english)?
+            .into_iter()
+            .find(|i| options.allows(&self.dictionary.entries[*i]))
+    }
+
+    // The k highest scoring English words starting with prefix that have allowed entries, best first,
+    // with those entries. A word scores as its best allowed entry, and one value is skipped.
+    // Values are visited best first by the score of their best entry,
+    // so we can stop once that's worse than all k found.
+    fn best_prefix_words(&self, prefix: &str, skip_value_rank: Option<usize>, k: usize, options: &SearchOptions) -> Vec<(usize, Vec<usize>)> {
+        if (k == 0) {
+            return Vec::new();
+        }
+
+        let weights = &options.ranking.clamped();
+
+        // Worst of the best so far first, ties going to the higher value rank
+        let mut best: Vec<(f64, usize, Vec<usize>)> = Vec::with_capacity(k + 1);
+        let worse = |(score_x, x, _): &(f64, usize, Vec<usize>), score_y: f64, y: usize| score_x.total_cmp(&score_y).then(y.cmp(x));
+
+        let values = self.trie.best_first(prefix, |node| self.node_scores[node].score(weights), |v| self.value_scores[v].score(weights));
+        for (value_rank, bound) in values {
+            if (best.len() == k && bound < best[0].0) {
+                break;
+            }
+
+            if (Some(value_rank) == skip_value_rank) {
+                continue;
+            }
+
+            let entry_ranks = self.allowed_entries(value_rank, options);
+            let score = match entry_ranks.iter().map(|i| self.entry_scores[*i].score(weights)).reduce(f64::max) {
+                Some(score) => score,
+                None => continue,
+            };
+
+            let at = best.partition_point(|x| worse(x, score, value_rank).is_lt());
+            best.insert(at, (score, value_rank, entry_ranks));
+            if (best.len() > k) {
+                best.remove(0);
+            }
+        }
+
+        best.into_iter().rev().map(|(_, value_rank, entry_ranks)| (value_rank, entry_ranks)).collect()
+    }
+
+    fn evaluate(&self, query: &Query, options: &SearchOptions) -> Result<CompoundWord, QueryError> {
+        match query {
+            Query::Word(w) => {
+                // Only the words as typed, a prefix would quietly combine whatever it completes to
+                let entry_rank = self.best_entry(w, options).ok_or_else(|| QueryError::UnknownWord(w.clone()))?;
+                Ok(self.dictionary.entries[entry_rank].toki_pona.clone())
+            }
+            Query::Union(x, y) => Ok(self.evaluate(x, options)?.union(&self.evaluate(y, options)?)),
+            Query::Intersection(x, y) => Ok(self.evaluate(x, options)?.intersection(&self.evaluate(y, options)?)),
+            Query::Difference(x, y) => Ok(self.evaluate(x, options)?.difference(&self.evaluate(y, options)?)),
+        }
+    }
+
+    // Evaluate a set-algebra query such as "poetry | cat" and search near the resulting compound.
+    pub fn query(&self, input: &str, options: &SearchOptions, pu: &Pu) -> Result<Completion, QueryError> {
+        let query = query::parse(input)?;
+        let toki_pona = self.evaluate(&query, options)?;
+
+        if (toki_pona.len() == 0) {
+            return Err(QueryError::EmptyCompound);
+        }
+
+        Ok(Completion {
+            english_search: input.to_owned(),
+            entry_english: query.to_string(),
+            entry_weight: 0,
+            original_translation_string: toki_pona.to_string(pu),
+            breakdown: toki_pona.glosses(pu),
+            source: TranslationSource::Synthesized,
+            alternatives: Vec::new(),
+            similar: self.find_similar(&toki_pona, options, pu),
+            correction: None,
+            lemma: None,
+            provenance: None,
+        })
+    }
+
+    // Search from a toki pona phrase back to English.
+    // Every exact translation is returned, followed by the closest near misses.
+    // Errors with the first word not found in pu.
+    pub fn reverse_lookup(&self, toki_pona: &str, options: &SearchOptions, pu: &Pu) -> Result<Vec<ThesaurusResult>, String> {
+        let compound = CompoundWord::parse(toki_pona, pu).map_err(|word| word.to_owned())?;
+        if (compound.len() == 0) {
+            return Ok(Vec::new());
+        }
+
+        // Only the same words in the same order are exact, the set distance can't tell
+        // "tomo tawa" from "tawa tomo" or "tomo pi tomo tawa"
+        let (exact, near): (Vec<Candidate>, Vec<Candidate>) = self.ranked_candidates(&compound, None, options)
+            .into_iter()
+            .partition(|c| self.dictionary.entries[c.entry_rank].toki_pona.toki_pona == compound.toki_pona);
+
+        Ok(exact.iter()
+            .chain(near.iter().take(options.similar))
+            .map(|c| self.thesaurus_result(c, &compound, pu))
+            .collect())
+    }
+
     pub fn lookup(&self, prefix: &str, pu: &Pu) -> Vec<Completion> {
+        self.lookup_with_options(prefix, &SearchOptions::default(), pu)
+    }
+
+    // Prefix search that falls back to completions within a few typos
+    // when there aren't enough real prefix matches.
+    pub fn lookup_with_options(&self, prefix: &str, options: &SearchOptions, pu: &Pu) -> Vec<Completion> {
         let mut completions = Vec::new();
         let normalized_prefix = prefix.to_lowercase();
-        let m_sub_trie = self.trie.get_raw_descendant(&normalized_prefix);
-
-        if (m_sub_trie.is_none()) {
-            return completions;
+        let max = options.completions;
+        let max_edits = options.max_edits_for(&normalized_prefix);
+
+        // (value rank, allowed entries of that English word, whether it matched by lemma),
+        // exact matches then lemma matches then other completions
+        let mut words = Vec::new();
+
+        let exact_value_rank = self.trie.get(&normalized_prefix);
+        words.extend(exact_value_rank.map(|v| (v, self.allowed_entries(v, options), false)));
+
+        // Inflections of the query that aren't already prefix matches, eg "hunted" -> "hunt"
+        let query_lemma = lemma::lemmatize(&normalized_prefix);
+        let mut lemma_words = Vec::new();
+        for value_rank in self.lemmas.get(&query_lemma).into_iter().flatten() {
+            let posting_list = &self.posting_lists[*value_rank];
+            if (!self.dictionary.entries[posting_list[0]].english.starts_with(&normalized_prefix)) {
+                lemma_words.push((*value_rank, self.allowed_entries(*value_rank, options)));
+            }
         }
 
-        let sub_trie = m_sub_trie.unwrap();
+        lemma_words.retain(|(_, entry_ranks)| !entry_ranks.is_empty());
+        lemma_words.sort_by_key(|(_, entry_ranks)| entry_ranks[0]);
+        words.extend(lemma_words.into_iter().map(|(v, x)| (v, x, true)));
+        words.retain(|(_, entry_ranks, _)| !entry_ranks.is_empty());
 
-        const MAX: usize = 5;
+        let remaining = max.saturating_sub(words.len());
+        words.extend(self.best_prefix_words(&normalized_prefix, exact_value_rank, remaining, options).into_iter().map(|(v, x)| (v, x, false)));
+        words.truncate(max);
 
-        let mut completion_and_entry_ranks = Vec::new();
+        // So typo corrections don't repeat words already found another way
+        let found: HashSet<usize> = words.iter().map(|(v, _, _)| *v).collect();
 
-        for (completion, value_rank) in sub_trie.iter() {
-            for entry_rank in &self.posting_lists[*value_rank] {
-                completion_and_entry_ranks.push((completion.to_string(), *entry_rank));
+        for (_, entry_ranks, by_lemma) in words {
+            let completion = if (by_lemma) { prefix } else { &self.dictionary.entries[entry_ranks[0]].english };
+            let mut c = self.populate_completion(completion, &entry_ranks, options, pu);
+            if (by_lemma) {
+                c.lemma = Some(query_lemma.clone());
             }
+
+            completions.push(c);
         }
 
-        completion_and_entry_ranks.sort_by(|(c_x, x), (c_y, y)| {
-            // Make sure that exact matches get bubbled to the top.
-            let exact_match_x = c_x == prefix;
-            let exact_match_y = c_y == prefix;
+        if (completions.len() < max && max_edits > 0) {
+            let mut fuzzy_words = Vec::new();
 
-            exact_match_y.cmp(&exact_match_x).then(x.cmp(y))
-        });
+            for m in self.trie.fuzzy_prefix(&normalized_prefix, max_edits) {
+                // Exact prefix matches and inflections have already been added above
+                if (m.edits == 0 || found.contains(&m.value)) {
+                    continue;
+                }
+
+                let entry_ranks = self.allowed_entries(m.value, options);
+                if (!entry_ranks.is_empty()) {
+                    fuzzy_words.push((m, entry_ranks));
+                }
+            }
+
+            // Fewest edits first
+            fuzzy_words.sort_by(|(m_x, x), (m_y, y)| {
+                m_x.edits.cmp(&m_y.edits).then(x[0].cmp(&y[0]))
+            });
 
-        for (completion, entry_rank) in completion_and_entry_ranks.iter().take(MAX) {
-            completions.push(self.populate_completion(completion, *entry_rank, pu));
+            for (m, entry_ranks) in fuzzy_words.into_iter().take(max - completions.len()) {
+                let mut completion = self.populate_completion(&m.key, &entry_ranks, options, pu);
+                completion.correction = Some(Correction {
+                    did_you_mean: m.corrected,
+                    edits: m.edits,
+                });
+                completions.push(completion);
+            }
         }
 
         completions
     }
 }
 
+// Allow more typos the longer the query, short prefixes match too much already.
+pub fn default_max_edits(prefix: &str) -> u32 {
+    match prefix.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+struct Candidate {
+    entry_rank: usize,
+    // Set distance from the query
+    dist: u32,
+    // Distance under the chosen metric
+    score: f64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ThesaurusResult {
     english: String,
     toki_pona_len : u32,
     toki_pona_string: String,
+    // Each word of toki_pona_string in order, with its part of speech and gloss
+    breakdown: Vec<WordGloss>,
     dist: u32,
+    score: f64,
     source : TranslationSource,
+    // Why the result is similar, toki pona words in both, or only in one
+    shared: Vec<WordGloss>,
+    only_query: Vec<WordGloss>,
+    only_result: Vec<WordGloss>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WordGloss {
+    toki_pona: String,
+    // From the word's first sense in pu
+    part_of_speech: Option<PartOfSpeech>,
+    gloss: String,
+    category: WordCategory,
+}
+
+impl WordGloss {
+    pub fn new(word: TokiPonaWord, pu: &Pu) -> Self {
+        let sense = pu.senses(&word).first();
+        WordGloss {
+            toki_pona: pu.get(&word).to_owned(),
+            part_of_speech: sense.and_then(|x| x.part_of_speech),
+            gloss: sense.map(|x| x.glosses.join(", ")).unwrap_or_default(),
+            category: pu.category(&word),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -343,14 +828,49 @@ pub struct Completion {
     entry_english: String,
     entry_weight : u32,
     original_translation_string: String,
+    breakdown: Vec<WordGloss>,
     source : TranslationSource,
+    // Other translations of the same English word, best ranked first
+    alternatives: Vec<AlternativeTranslation>,
+    // Near any of the translations, once per English word
     similar: Vec<ThesaurusResult>,
+    // Set when the completion only matched after correcting typos
+    correction: Option<Correction>,
+    // Set when the entry matched an inflection of the search rather than the search itself
+    lemma: Option<String>,
+    // Set when the search wasn't in the dictionary and this is a proposed translation
+    provenance: Option<fallback::Provenance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlternativeTranslation {
+    toki_pona_string: String,
+    breakdown: Vec<WordGloss>,
+    weight: u32,
+    source: TranslationSource,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Correction {
+    did_you_mean: String,
+    edits: u32,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Compound translations in the nimi pu format, eg "toki musi: [poetry 50, joke 40]"
+    pub(crate) fn entries(pu: &Pu, lines: &[&str]) -> Vec<Translation> {
+        lines.iter()
+            .flat_map(|line| Translation::try_parse(line, pu, TranslationSource::Compounds).unwrap())
+            .collect()
+    }
+
+    pub(crate) fn toki_sama(pu: &Pu, lines: &[&str]) -> TokiSama {
+        TokiSama::new(Dictionary { entries: entries(pu, lines) })
+    }
+
     #[test]
     fn parse_translation() {
         let pu = Pu::from_subset(&[("lipu", "paper"), ("moku", "food")]);
@@ -358,4 +878,178 @@ mod tests {
         assert!(parsed.is_some());
         assert_eq!("menu", parsed.unwrap()[0].english);
     }
+
+    #[test]
+    fn structured_compounds() {
+        let pu = Pu::from_subset(&[("jan", "person"), ("pi", "of"), ("kama", "coming"), ("sona", "knowledge"), ("tomo", "house"), ("tawa", "moving")]);
+
+        let student = CompoundWord::parse("jan pi kama sona", &pu).unwrap();
+        assert_eq!(pu.lookup("jan"), student.head());
+        assert!(student.modifiers().is_empty());
+        assert_eq!(&[WordSet::from_words(&[pu.lookup("kama").unwrap(), pu.lookup("sona").unwrap()])], student.pi_groups());
+        assert_eq!("jan pi kama sona", student.to_string(&pu));
+
+        let pi = CompoundWord::parse("pi", &pu).unwrap();
+        assert_eq!(pu.lookup("pi"), pi.head());
+        assert_eq!(1, pi.words().len());
+
+        let car = CompoundWord::parse("tomo tawa", &pu).unwrap();
+        let reversed = CompoundWord::parse("tawa tomo", &pu).unwrap();
+        let house = CompoundWord::parse("tomo", &pu).unwrap();
+
+        // Order free distance can't tell them apart
+        assert_eq!(0, car.dist(&reversed));
+
+        let structured = distance::Structured::default();
+        assert_eq!(0.0, structured.dist(&car, &car));
+        assert!(structured.dist(&car, &house) < structured.dist(&car, &reversed));
+    }
+
+    #[test]
+    fn reverse_lookup_exact_then_near() {
+        let pu = Pu::from_subset(&[("tomo", "house"), ("tawa", "moving"), ("lili", "small"), ("pi", "of")]);
+        let toki_sama = toki_sama(&pu, &[
+            "tomo tawa: [car 90, vehicle 60]",
+            "tawa tomo: [moving house 20]",
+            "tomo: [house 80]",
+            "tomo lili: [hut 50]",
+            "tomo pi tomo tawa: [garage 40]",
+        ]);
+
+        let english = |options: &SearchOptions| -> Vec<String> {
+            toki_sama.reverse_lookup("tomo tawa", options, &pu).unwrap().into_iter().map(|r| r.english).collect()
+        };
+
+        // The same words in another order or grouping are only near misses
+        let options = SearchOptions { similar: 3, ..SearchOptions::default() };
+        let found = english(&options);
+        assert_eq!(5, found.len());
+        assert_eq!(vec!["car", "vehicle"], found[..2]);
+        assert!(found.contains(&"moving house".to_owned()) && found.contains(&"garage".to_owned()));
+
+        // Word order puts "tomo" closer than "tawa tomo"
+        let structured = SearchOptions { metric: Metric::Structured, similar: 1, ..options.clone() };
+        assert_eq!(vec!["car", "vehicle", "house"], english(&structured));
+
+        assert_eq!(Err("nimi".to_owned()), toki_sama.reverse_lookup("tomo nimi", &options, &pu).map(|_| ()));
+    }
+
+    #[test]
+    fn query_operands() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("soweli", "animal")]);
+        let mut entries = entries(&pu, &[
+            "toki musi: [poetry 80]",
+            "soweli: [cat 70]",
+            "soweli musi: [pets 40]",
+        ]);
+        entries[2].source = TranslationSource::Generated;
+
+        let toki_sama = TokiSama::new(Dictionary { entries });
+        let options = SearchOptions::default();
+        let translation = |input: &str, options: &SearchOptions| toki_sama.query(input, options, &pu).map(|c| c.original_translation_string);
+
+        assert_eq!(Ok("toki musi soweli".to_owned()), translation("poetry | cat", &options));
+        assert_eq!(Ok("musi".to_owned()), translation("poetry & pet", &options));
+
+        // Not whatever "ca" completes to
+        assert_eq!(Err(QueryError::UnknownWord("ca".to_owned())), translation("poetry | ca", &options));
+
+        let compounds = SearchOptions { sources: vec![TranslationSource::Compounds], ..SearchOptions::default() };
+        assert_eq!(Err(QueryError::UnknownWord("pet".to_owned())), translation("poetry & pet", &compounds));
+    }
+
+    #[test]
+    fn prefix_lookup_order() {
+        let pu = Pu::from_subset(&[("telo", "water"), ("jan", "person"), ("sona", "knowledge"), ("tomo", "house"), ("len", "cloth")]);
+        let lines = [
+            "jan sona: [teacher 95, teaching 60]",
+            "telo: [water 90, tea 20]",
+            "tomo len: [tent 50]",
+            "sona: [technique 40]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let options = SearchOptions { completions: 3, ..SearchOptions::default() };
+        let english = |prefix: &str| toki_sama.lookup_with_options(prefix, &options, &pu)
+            .into_iter()
+            .map(|c| c.entry_english)
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["teacher", "teaching", "tent"], english("te"));
+
+        // The exact match comes first however it's typed, even though it's the weakest entry
+        assert_eq!(vec!["tea", "teacher", "teaching"], english("Tea"));
+    }
+
+    #[test]
+    fn corrections_skip_words_already_found() {
+        let pu = Pu::from_subset(&[("ma", "land"), ("anpa", "down"), ("tawa", "moving")]);
+        let toki_sama = toki_sama(&pu, &[
+            "ma: [land 90]",
+            "tawa anpa: [landing 60, lands 40]",
+        ]);
+
+        let completions = toki_sama.lookup("landings", &pu);
+        let english: Vec<&str> = completions.iter().map(|c| c.entry_english.as_str()).collect();
+        assert_eq!(vec!["land", "landing", "lands"], english);
+        assert!(completions.iter().all(|c| c.correction.is_none()));
+    }
+
+    #[test]
+    fn word_level_completions() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("pona", "good"), ("kalama", "sound"), ("lipu", "document")]);
+        let lines = [
+            "toki musi: [poetry 80, poem 50]",
+            "toki pona: [poetry 30, proverb 40]",
+            "kalama musi: [song 70, poem 20]",
+            "lipu: [page 60]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let completions = toki_sama.lookup_with_options("po", &SearchOptions::default(), &pu);
+
+        // One completion per English word, however many translations it has
+        let english: Vec<&str> = completions.iter().map(|c| c.entry_english.as_str()).collect();
+        assert_eq!(vec!["poetry", "poem"], english);
+
+        let poetry = &completions[0];
+        assert_eq!("toki musi", poetry.original_translation_string);
+        assert_eq!(vec!["toki pona"], poetry.alternatives.iter().map(|a| a.toki_pona_string.as_str()).collect::<Vec<_>>());
+        assert_eq!(30, poetry.alternatives[0].weight);
+
+        // Near either translation, each word once and never poetry itself
+        let similar: Vec<&str> = poetry.similar.iter().map(|s| s.english.as_str()).collect();
+        assert!(similar.contains(&"proverb") && similar.contains(&"song"));
+        assert_eq!(1, similar.iter().filter(|x| **x == "poem").count());
+        assert!(!similar.contains(&"poetry"));
+    }
+
+    #[test]
+    fn similar_words_explain_differences() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("kalama", "sound")]);
+        let lines = [
+            "toki musi: [poetry 80, joke 40]",
+            "musi: [game 70]",
+            "kalama musi: [song 60]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let similar = &toki_sama.lookup_with_options("poetry", &SearchOptions::default(), &pu)[0].similar;
+        let result = |english: &str| similar.iter().find(|s| s.english == english).unwrap();
+        let words = |glosses: &[WordGloss]| glosses.iter().map(|g| g.toki_pona.as_str()).collect::<Vec<_>>().join(" ");
+
+        let joke = result("joke");
+        assert_eq!("toki musi", words(&joke.shared));
+        assert!(joke.only_query.is_empty() && joke.only_result.is_empty());
+
+        let game = result("game");
+        assert_eq!("musi", words(&game.shared));
+        assert_eq!("toki", words(&game.only_query));
+        assert!(game.only_result.is_empty());
+
+        let song = result("song");
+        assert_eq!("musi", words(&song.shared));
+        assert_eq!("toki", words(&song.only_query));
+        assert_eq!("kalama", words(&song.only_result));
+    }
 }
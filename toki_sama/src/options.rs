@@ -0,0 +1,134 @@
+use serde::{Serialize, Deserialize};
+
+use crate::distance::Metric;
+use crate::popularity::RankingWeights;
+use crate::pu::WordCategory;
+use crate::{Translation, TranslationSource};
+
+// Knobs for a search, so each frontend can tune how much comes back.
+// Missing fields take their default when deserialized, so frontends can pass just what they change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    // Number of English completions of the prefix
+    pub completions: usize,
+    // Number of similar words per completion
+    pub similar: usize,
+    // Furthest set distance a similar word can be, whatever the metric.
+    // None scales with the size of the compound searched for.
+    pub max_dist: Option<u32>,
+    // How similar words within max_dist are ordered, it doesn't change which are found
+    pub metric: Metric,
+    // Typos allowed when there aren't enough prefix matches.
+    // None scales with the length of the prefix.
+    pub max_edits: Option<u32>,
+    // Only entries from these sources are searched
+    pub sources: Vec<TranslationSource>,
+    pub min_weight: u32,
+    // Only entries whose least established word is in one of these categories,
+    // eg just pu to leave out compounds using ku words
+    pub categories: Vec<WordCategory>,
+    // Bounds on the number of toki pona words in an entry
+    pub min_len: usize,
+    pub max_len: usize,
+    // How prefix completions are ordered after exact and lemma matches
+    pub ranking: RankingWeights,
+    // From 0 for the closest similar words whatever they are,
+    // up to 1 to prefer similar words unlike those already shown
+    pub diversity: f64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            completions: 5,
+            similar: 5,
+            max_dist: None,
+            metric: Metric::default(),
+            max_edits: None,
+            sources: vec![TranslationSource::NimiPu, TranslationSource::Compounds, TranslationSource::Generated],
+            min_weight: 0,
+            categories: vec![WordCategory::Pu, WordCategory::KuSuli, WordCategory::KuLili, WordCategory::Sin],
+            min_len: 0,
+            max_len: usize::MAX,
+            ranking: RankingWeights::default(),
+            diversity: 0.5,
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn allows(&self, entry: &Translation) -> bool {
+        let len = entry.toki_pona.len();
+        self.sources.contains(&entry.source)
+            && entry.weight >= self.min_weight
+            && self.categories.contains(&entry.category)
+            && len >= self.min_len
+            && len <= self.max_len
+    }
+
+    pub fn max_dist_for(&self, compound_len: usize) -> u32 {
+        self.max_dist.unwrap_or(compound_len.max(1) as u32)
+    }
+
+    pub fn max_edits_for(&self, prefix: &str) -> u32 {
+        self.max_edits.unwrap_or_else(|| crate::default_max_edits(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pu::Pu;
+    use crate::tests::entries;
+    use crate::{Dictionary, TokiSama};
+
+    #[test]
+    fn options_filter_lookups() {
+        let pu = Pu::from_subset(&[("pona", "good"), ("mute", "many"), ("a", "ah"), ("ike", "bad")]);
+        let mut entries = entries(&pu, &[
+            "pona: [good 90]",
+            "pona mute: [great 60]",
+            "pona mute a: [excellent 30]",
+            "ike: [bad 80]",
+        ]);
+        entries[2].source = TranslationSource::Generated;
+
+        let toki_sama = TokiSama::new(Dictionary { entries });
+        let found = |english: &str, options: &SearchOptions| toki_sama.lookup_with_options(english, options, &pu)
+            .iter()
+            .any(|c| c.entry_english == english);
+        let similar = |english: &str, options: &SearchOptions| {
+            let mut similar: Vec<String> = toki_sama.lookup_with_options(english, options, &pu)[0].similar
+                .iter()
+                .map(|s| s.english.clone())
+                .collect();
+            similar.sort();
+            similar
+        };
+
+        let options = SearchOptions::default();
+        assert!(found("excellent", &options) && found("great", &options) && found("good", &options));
+
+        let options = SearchOptions { sources: vec![TranslationSource::Compounds], ..SearchOptions::default() };
+        assert!(!found("excellent", &options) && found("great", &options));
+
+        let options = SearchOptions { min_weight: 70, ..SearchOptions::default() };
+        assert!(!found("great", &options) && found("good", &options));
+
+        let options = SearchOptions { min_len: 2, ..SearchOptions::default() };
+        assert!(!found("good", &options) && found("great", &options));
+
+        let options = SearchOptions { max_len: 2, ..SearchOptions::default() };
+        assert!(!found("excellent", &options) && found("great", &options));
+
+        // Scales with the compound unless set
+        let options = SearchOptions { diversity: 0.0, ..SearchOptions::default() };
+        assert_eq!(3, options.max_dist_for(3));
+        assert_eq!(vec!["great"], similar("good", &options));
+
+        let options = SearchOptions { max_dist: Some(2), ..options };
+        assert_eq!(2, options.max_dist_for(3));
+        assert_eq!(vec!["bad", "excellent", "great"], similar("good", &options));
+    }
+}
@@ -0,0 +1,205 @@
+// Ranking prefix completions by how likely they are to be the word being typed.
+// A completion's score blends how common the English word is, how strongly it's translated
+// and how much we trust where the translation came from, so typing "te" suggests "teacher"
+// before "tetrahedron" even if the rarer word has a better ranked entry.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Translation, TranslationSource};
+
+// English word counts from a frequency list, one "word count" per line eg "the 23135851162".
+// Lines starting with '#' are comments.
+#[derive(Debug, Default)]
+pub struct Frequencies {
+    counts: HashMap<String, u64>,
+    max_count: u64,
+}
+
+impl Frequencies {
+    pub fn from_lines(lines: &[String]) -> Self {
+        let mut counts = HashMap::with_capacity(lines.len());
+        let mut max_count = 0;
+
+        for line in lines {
+            let line = line.trim();
+            if (line.is_empty() || line.starts_with('#')) {
+                continue;
+            }
+
+            let parsed = line.rsplit_once(|c: char| c.is_whitespace() || c == ',')
+                .and_then(|(word, count)| Some((word.trim().to_lowercase(), count.trim().parse::<u64>().ok()?)));
+
+            match parsed {
+                Some((word, count)) => {
+                    max_count = max_count.max(count);
+                    *counts.entry(word).or_insert(0) += count;
+                }
+                None => println!("Could not parse frequency line: {}", line),
+            }
+        }
+
+        Frequencies {
+            counts,
+            max_count,
+        }
+    }
+
+    pub fn read(path: &Path) -> Self {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        let lines: Vec<String> = reader.lines().map(|x| x.unwrap()).collect();
+
+        Self::from_lines(&lines)
+    }
+
+    // From 0 for unknown words to 1 for the most common, on a log scale as counts fall off so quickly.
+    // A phrase is as popular as its rarest word.
+    pub fn popularity(&self, english: &str) -> f64 {
+        if (self.max_count == 0) {
+            return 0.0;
+        }
+
+        let max = (self.max_count as f64).ln_1p();
+        english.split(|c: char| c.is_whitespace() || c == '-')
+            .filter(|x| !x.is_empty())
+            .map(|word| self.counts.get(&word.to_lowercase()).map(|count| (*count as f64).ln_1p() / max).unwrap_or(0.0))
+            .fold(None, |rarest: Option<f64>, x| Some(rarest.map_or(x, |r| r.min(x))))
+            .unwrap_or(0.0)
+    }
+}
+
+// How much each part counts towards a completion's score.
+// None can be negative, completions are searched for by the best parts under each trie node,
+// which only bounds the score if more of a part never counts against it.
+// Negative or non-finite weights are treated as 0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    pub popularity: f64,
+    pub weight: f64,
+    pub source: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        RankingWeights {
+            popularity: 0.5,
+            weight: 0.3,
+            source: 0.2,
+        }
+    }
+}
+
+// The parts of a completion's score, each from 0 to 1.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScoreParts {
+    popularity: f64,
+    // Relative to the heaviest entry from the same source, weights from different sources aren't comparable
+    weight: f64,
+    source: f64,
+}
+
+impl RankingWeights {
+    pub fn is_valid_weight(weight: f64) -> bool {
+        weight.is_finite() && weight >= 0.0
+    }
+
+    pub fn is_valid(&self) -> bool {
+        [self.popularity, self.weight, self.source].iter().all(|x| Self::is_valid_weight(*x))
+    }
+
+    // Invalid weights count for nothing, so the search bound still holds whatever the caller set
+    pub(crate) fn clamped(&self) -> Self {
+        let clamp = |x: f64| if (Self::is_valid_weight(x)) { x } else { 0.0 };
+        RankingWeights {
+            popularity: clamp(self.popularity),
+            weight: clamp(self.weight),
+            source: clamp(self.source),
+        }
+    }
+}
+
+impl ScoreParts {
+    // max_weights is the heaviest entry of each source, indexed by source
+    pub(crate) fn new(entry: &Translation, max_weights: &[u32], frequencies: &Frequencies) -> Self {
+        ScoreParts {
+            popularity: frequencies.popularity(&entry.english),
+            weight: entry.weight as f64 / max_weights[entry.source as usize].max(1) as f64,
+            source: source_trust(entry.source),
+        }
+    }
+
+    // Each part at its largest, so the score is at least that of either
+    pub(crate) fn max(self, other: Self) -> Self {
+        ScoreParts {
+            popularity: self.popularity.max(other.popularity),
+            weight: self.weight.max(other.weight),
+            source: self.source.max(other.source),
+        }
+    }
+
+    pub(crate) fn score(&self, weights: &RankingWeights) -> f64 {
+        weights.popularity * self.popularity + weights.weight * self.weight + weights.source * self.source
+    }
+}
+
+fn source_trust(source: TranslationSource) -> f64 {
+    match source {
+        TranslationSource::NimiPu => 1.0,
+        TranslationSource::Compounds => 0.75,
+        TranslationSource::Generated => 0.25,
+        TranslationSource::Synthesized => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SearchOptions;
+    use crate::pu::Pu;
+    use crate::tests::entries;
+    use crate::{Dictionary, TokiSama};
+
+    #[test]
+    fn popular_completions_first() {
+        let pu = Pu::from_subset(&[("jan", "person"), ("sona", "knowledge"), ("leko", "block"), ("mute", "many")]);
+        let lines = [
+            "leko mute: [tetrahedron 90]",
+            "jan sona: [teacher 60]",
+        ];
+
+        let entries = entries(&pu, &lines);
+
+        let frequencies = Frequencies::from_lines(&["# word count".to_owned(), "teacher 52000".to_owned(), "tetrahedron,120".to_owned()]);
+        assert!(frequencies.popularity("teacher") > frequencies.popularity("tetrahedron"));
+        assert_eq!(0.0, frequencies.popularity("teacher tetrahedra"));
+
+        let english = |toki_sama: &TokiSama, options: &SearchOptions| toki_sama.lookup_with_options("te", options, &pu)
+            .into_iter()
+            .map(|c| c.entry_english)
+            .collect::<Vec<_>>();
+
+        let options = SearchOptions::default();
+        let toki_sama = TokiSama::new(Dictionary { entries: entries.clone() });
+        assert_eq!(vec!["tetrahedron", "teacher"], english(&toki_sama, &options));
+
+        let toki_sama = TokiSama::with_frequencies(Dictionary { entries }, &frequencies);
+        assert_eq!(vec!["teacher", "tetrahedron"], english(&toki_sama, &options));
+
+        let options = SearchOptions { ranking: RankingWeights { popularity: 0.0, ..Default::default() }, ..SearchOptions::default() };
+        assert_eq!(vec!["tetrahedron", "teacher"], english(&toki_sama, &options));
+
+        assert!(options.ranking.is_valid());
+        assert!(!RankingWeights { weight: -1.0, ..Default::default() }.is_valid());
+        assert!(!RankingWeights { source: f64::NAN, ..Default::default() }.is_valid());
+
+        // Searched as if they were 0 rather than breaking the bound
+        let negative = SearchOptions { ranking: RankingWeights { popularity: -1.0, ..Default::default() }, ..SearchOptions::default() };
+        assert_eq!(vec!["tetrahedron", "teacher"], english(&toki_sama, &negative));
+    }
+}
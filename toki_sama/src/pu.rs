@@ -2,18 +2,166 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+use crate::word_set::WordSet;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct TokiPonaWord(u8);
+pub struct TokiPonaWord(u16);
+
+impl TokiPonaWord {
+    pub(crate) fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    pub(crate) fn from_index(index: usize) -> Self {
+        TokiPonaWord(index as u16)
+    }
+}
+
+// Which book, if any, a word comes from. Ordered from most to least established.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordCategory {
+    Pu,
+    KuSuli,
+    KuLili,
+    // Newer than ku or unofficial
+    Sin,
+}
+
+impl FromStr for WordCategory {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pu" => Ok(WordCategory::Pu),
+            "ku suli" | "ku_suli" => Ok(WordCategory::KuSuli),
+            "ku lili" | "ku_lili" => Ok(WordCategory::KuLili),
+            "sin" => Ok(WordCategory::Sin),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+pub enum PartOfSpeech {
+    Noun,
+    Verb,
+    Adjective,
+    PreVerb,
+    Preposition,
+    Particle,
+    Number,
+}
+
+impl FromStr for PartOfSpeech {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NOUN" => Ok(PartOfSpeech::Noun),
+            "VERB" => Ok(PartOfSpeech::Verb),
+            "ADJECTIVE" => Ok(PartOfSpeech::Adjective),
+            "PRE-VERB" => Ok(PartOfSpeech::PreVerb),
+            "PREPOSITION" => Ok(PartOfSpeech::Preposition),
+            "PARTICLE" => Ok(PartOfSpeech::Particle),
+            "NUMBER" => Ok(PartOfSpeech::Number),
+            _ => Err(()),
+        }
+    }
+}
+
+// One use of a word, eg "NOUN abundance, everything, life, universe"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Sense {
+    // A few definitions don't say
+    pub part_of_speech: Option<PartOfSpeech>,
+    pub glosses: Vec<String>,
+}
 
 #[derive(Debug, Clone)]
 struct PuEntry {
     toki_pona: String,
     definition: String,
+    senses: Vec<Sense>,
+    category: WordCategory,
 }
 
-// The standard english <-> toki pona dictioanry
-// TODO include ku words
+// Split a csv line into fields, fields in double quotes can contain commas and "" for a quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    fields.push(field);
+    fields
+}
+
+// Split on separators that aren't inside parentheses
+fn split_glosses(glosses: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut gloss = String::new();
+    let mut depth = 0;
+
+    for c in glosses.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+
+        if (depth == 0 && (c == ',' || c == ';')) {
+            split.push(std::mem::take(&mut gloss));
+        }
+        else {
+            gloss.push(c);
+        }
+    }
+
+    split.push(gloss);
+    split.iter().map(|x| x.trim().to_owned()).filter(|x| !x.is_empty()).collect()
+}
+
+// "ADJECTIVE all; abundant NOUN everything NUMBER 100" -> [ADJECTIVE [all, abundant], NOUN [everything], NUMBER [100]]
+fn parse_senses(definition: &str) -> Vec<Sense> {
+    let mut senses: Vec<(Option<PartOfSpeech>, String)> = Vec::new();
+
+    for word in definition.split_whitespace() {
+        match (word.parse().ok(), senses.last_mut()) {
+            (Some(part_of_speech), _) => senses.push((Some(part_of_speech), String::new())),
+            (None, Some((_, text))) => {
+                if (!text.is_empty()) {
+                    text.push(' ');
+                }
+                text.push_str(word);
+            }
+            (None, None) => senses.push((None, word.to_owned())),
+        }
+    }
+
+    senses.into_iter()
+        .map(|(part_of_speech, text)| Sense { part_of_speech, glosses: split_glosses(&text) })
+        .collect()
+}
+
+// The standard english <-> toki pona dictioanry, plus any ku or newer words loaded on top.
 // We use this to get string representations of TokiPonaWords and output full definitinos
 pub struct Pu {
     lookup: HashMap<String, TokiPonaWord>,
@@ -21,30 +169,62 @@ pub struct Pu {
 }
 
 impl Pu {
-    pub fn from_lines(lines : &Vec<String>) -> Self {
-        let mut lookup = HashMap::with_capacity(lines.len());
-        let mut definitions = Vec::with_capacity(lines.len());
+    pub fn from_lines(lines : &[String]) -> Self {
+        let mut pu = Pu {
+            lookup: HashMap::with_capacity(lines.len()),
+            definitions: Vec::with_capacity(lines.len()),
+        };
 
-        // First line is definitions
-        let mut cur_word = 0;
-        for line in &lines[1..] {
-            let splits: Vec<&str> = line.split(',').collect();
-            let toki_pona = splits[0].to_owned();
-            let _alternative = splits[1];
-            let definition = splits[2].to_owned();
+        pu.extend_from_lines(lines);
+        pu
+    }
 
-            lookup.insert(toki_pona.clone(), TokiPonaWord(cur_word));
-            definitions.push(PuEntry {
+    // Add words from a csv in the same format as pu.csv, with an optional fourth column
+    // giving the category, eg "ku suli". Words without one are pu words.
+    // Words already known are skipped.
+    pub fn extend_from_lines(&mut self, lines : &[String]) {
+        // First line is the header
+        for line in lines.iter().skip(1).filter(|x| !x.trim().is_empty()) {
+            let fields = split_csv_line(line);
+            let field = |i: usize| fields.get(i).map(|x| x.trim()).unwrap_or_default();
+
+            let toki_pona = field(0).to_owned();
+            if (self.lookup.contains_key(&toki_pona)) {
+                continue;
+            }
+
+            if (self.definitions.len() >= WordSet::CAPACITY) {
+                println!("Too many toki pona words, skipping {}", toki_pona);
+                continue;
+            }
+
+            let category = match field(3) {
+                "" => WordCategory::Pu,
+                x => match x.parse() {
+                    Ok(category) => category,
+                    Err(()) => {
+                        println!("Unknown word category {} for {}", x, toki_pona);
+                        continue;
+                    }
+                },
+            };
+
+            let word = TokiPonaWord(self.definitions.len() as u16);
+            let definition = field(2).to_owned();
+
+            self.lookup.insert(toki_pona.clone(), word);
+
+            // Other spellings, "ali" for "ale"
+            if (!field(1).is_empty()) {
+                self.lookup.entry(field(1).to_owned()).or_insert(word);
+            }
+
+            self.definitions.push(PuEntry {
                 toki_pona,
+                senses: parse_senses(&definition),
                 definition,
+                category,
             });
-
-            cur_word += 1;
-        }
-
-        Pu {
-            lookup,
-            definitions,
         }
     }
 
@@ -52,7 +232,16 @@ impl Pu {
         let file = File::open(path).unwrap();
         let reader = BufReader::new(file);
 
-        Self::from_lines(&reader.lines().map(|x| x.unwrap()).collect())
+        let lines: Vec<String> = reader.lines().map(|x| x.unwrap()).collect();
+        Self::from_lines(&lines)
+    }
+
+    pub fn read_extra(&mut self, path: &Path) {
+        let file = File::open(path).unwrap();
+        let reader = BufReader::new(file);
+
+        let lines: Vec<String> = reader.lines().map(|x| x.unwrap()).collect();
+        self.extend_from_lines(&lines);
     }
 
     #[cfg(test)]
@@ -66,6 +255,8 @@ impl Pu {
             definitions.push(PuEntry {
                 toki_pona: toki_pona.to_string(),
                 definition: english.to_string(),
+                senses: vec![Sense { part_of_speech: None, glosses: vec![english.to_string()] }],
+                category: WordCategory::Pu,
             });
 
             cur_word += 1;
@@ -85,7 +276,90 @@ impl Pu {
         &self.definitions[word.0 as usize].definition
     }
 
+    pub fn senses(&self, word: &TokiPonaWord) -> &[Sense] {
+        &self.definitions[word.0 as usize].senses
+    }
+
+    pub fn category(&self, word: &TokiPonaWord) -> WordCategory {
+        self.definitions[word.0 as usize].category
+    }
+
+    // Canonical spellings and alternatives both resolve
+    // to the same word, "ali" is "ale".
     pub fn lookup(&self, s: &str) -> Option<TokiPonaWord> {
         self.lookup.get(s).cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SearchOptions;
+    use crate::tests::toki_sama;
+    use crate::{CompoundWord, Translation, TranslationSource};
+
+    #[test]
+    fn gloss_breakdown() {
+        let lines: Vec<String> = [
+            "Word, Alternative, Definition",
+            "ale,ali,\"ADJECTIVE all; abundant, every NOUN everything NUMBER 100\"",
+            "jan,,\"NOUN human being, person, somebody\"",
+            "pi,,\"PARTICLE (between a head and a \"\"group\"\", of)\"",
+            "sin,namako,\"new, fresh\"",
+            "sona,,\"NOUN knowledge\"",
+        ].iter().map(|x| x.to_string()).collect();
+        let pu = Pu::from_lines(&lines);
+
+        let ale = pu.lookup("ale").unwrap();
+        assert_eq!(Some(ale), pu.lookup("ali"));
+        assert_eq!("ale", pu.get(&ale));
+        assert_eq!(vec![
+            Sense { part_of_speech: Some(PartOfSpeech::Adjective), glosses: vec!["all".to_owned(), "abundant".to_owned(), "every".to_owned()] },
+            Sense { part_of_speech: Some(PartOfSpeech::Noun), glosses: vec!["everything".to_owned()] },
+            Sense { part_of_speech: Some(PartOfSpeech::Number), glosses: vec!["100".to_owned()] },
+        ], pu.senses(&ale));
+
+        let sin = pu.lookup("namako").unwrap();
+        assert_eq!(None, pu.senses(&sin)[0].part_of_speech);
+
+        let glosses = CompoundWord::parse("jan pi sona", &pu).unwrap().glosses(&pu);
+        assert_eq!(vec!["jan", "pi", "sona"], glosses.iter().map(|g| g.toki_pona.as_str()).collect::<Vec<_>>());
+        assert_eq!("human being, person, somebody", glosses[0].gloss);
+        assert_eq!(Some(PartOfSpeech::Particle), glosses[1].part_of_speech);
+        assert_eq!("(between a head and a \"group\", of)", glosses[1].gloss);
+    }
+
+    #[test]
+    fn extra_word_categories() {
+        // More words than a u8 could index
+        let mut lines = vec!["Word, Alternative, Definition".to_owned()];
+        lines.extend((0..300).map(|i| format!("nimi{},,NOUN word {}", i, i)));
+        lines.push("soweli,,NOUN animal".to_owned());
+        let mut pu = Pu::from_lines(&lines);
+
+        let extra: Vec<String> = [
+            "Word, Alternative, Definition, Category",
+            "kijetesantakalu,,\"NOUN raccoon, procyonid\",ku suli",
+            "soweli,,NOUN not a ku word,ku lili",
+        ].iter().map(|x| x.to_string()).collect();
+        pu.extend_from_lines(&extra);
+
+        let raccoon = pu.lookup("kijetesantakalu").unwrap();
+        assert_eq!(301, raccoon.index());
+        assert_eq!(WordCategory::KuSuli, pu.category(&raccoon));
+        assert_eq!(WordCategory::Pu, pu.category(&pu.lookup("soweli").unwrap()));
+
+        let entries = Translation::try_parse("soweli kijetesantakalu: [raccoon 50]", &pu, TranslationSource::Compounds).unwrap();
+        assert_eq!(WordCategory::KuSuli, entries[0].category);
+
+        let mut options = SearchOptions::default();
+        assert!(options.allows(&entries[0]));
+        options.categories = vec![WordCategory::Pu];
+        assert!(!options.allows(&entries[0]));
+
+        // Words past the first 256 go through every index built over the dictionary
+        let toki_sama = toki_sama(&pu, &["nimi299: [thing 50]", "soweli kijetesantakalu: [raccoon 50]"]);
+        let completions = toki_sama.lookup("thing", &pu);
+        assert_eq!("nimi299", completions[0].original_translation_string);
+    }
+}
@@ -0,0 +1,237 @@
+// A small set-algebra language over English words.
+//
+//   poetry | cat      union
+//   food & animal     intersection
+//   house - big       difference
+//   (a | b) & c       grouping
+//
+// '&' binds tighter than '|' and '-', which are left associative.
+// Operands are looked up as English words and their translations combined.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Word(String),
+    Union(Box<Query>, Box<Query>),
+    Intersection(Box<Query>, Box<Query>),
+    Difference(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    UnknownWord(String),
+    EmptyCompound,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::UnexpectedEnd => write!(f, "unexpected end of query"),
+            QueryError::UnexpectedToken(t) => write!(f, "unexpected '{}'", t),
+            QueryError::UnknownWord(w) => write!(f, "no translation for '{}'", w),
+            QueryError::EmptyCompound => write!(f, "query results in an empty compound"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Or,
+    And,
+    Minus,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "{}", w),
+            Token::Or => write!(f, "|"),
+            Token::And => write!(f, "&"),
+            Token::Minus => write!(f, "-"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+
+    fn flush(word: &mut String, tokens: &mut Vec<Token>) {
+        let normalized = word.split_whitespace().collect::<Vec<_>>().join(" ");
+        if (!normalized.is_empty()) {
+            tokens.push(Token::Word(normalized));
+        }
+
+        word.clear();
+    }
+
+    for i in 0..chars.len() {
+        let c = chars[i];
+        let op = match c {
+            '|' => Some(Token::Or),
+            '&' => Some(Token::And),
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            '-' => {
+                // Hyphenated words like "uh-huh" are a single operand.
+                let joins_word = i > 0 && chars[i - 1].is_alphanumeric()
+                    && i + 1 < chars.len() && chars[i + 1].is_alphanumeric();
+                if (joins_word) {
+                    None
+                }
+                else {
+                    Some(Token::Minus)
+                }
+            }
+            _ => None,
+        };
+
+        match op {
+            Some(token) => {
+                flush(&mut word, &mut tokens);
+                tokens.push(token);
+            }
+            None => {
+                word.push(c);
+            }
+        }
+    }
+
+    flush(&mut word, &mut tokens);
+    tokens
+}
+
+// Whether the input uses any operators, as opposed to being a plain prefix search.
+pub fn is_query(input: &str) -> bool {
+    tokenize(input).iter().any(|t| !matches!(t, Token::Word(_)))
+}
+
+pub fn parse(input: &str) -> Result<Query, QueryError> {
+    let tokens = tokenize(input);
+    let mut parser = Parser { tokens, pos: 0 };
+    let query = parser.parse_expr()?;
+
+    match parser.next() {
+        Some(t) => Err(QueryError::UnexpectedToken(t.to_string())),
+        None => Ok(query),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_expr(&mut self) -> Result<Query, QueryError> {
+        let mut lhs = self.parse_intersection()?;
+
+        loop {
+            match self.peek() {
+                Some(Token::Or) => {
+                    self.pos += 1;
+                    let rhs = self.parse_intersection()?;
+                    lhs = Query::Union(Box::new(lhs), Box::new(rhs));
+                }
+                Some(Token::Minus) => {
+                    self.pos += 1;
+                    let rhs = self.parse_intersection()?;
+                    lhs = Query::Difference(Box::new(lhs), Box::new(rhs));
+                }
+                _ => {
+                    return Ok(lhs);
+                }
+            }
+        }
+    }
+
+    fn parse_intersection(&mut self) -> Result<Query, QueryError> {
+        let mut lhs = self.parse_atom()?;
+
+        while let Some(Token::And) = self.peek() {
+            self.pos += 1;
+            let rhs = self.parse_atom()?;
+            lhs = Query::Intersection(Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_atom(&mut self) -> Result<Query, QueryError> {
+        match self.next() {
+            Some(Token::Word(w)) => Ok(Query::Word(w)),
+            Some(Token::Open) => {
+                let inner = self.parse_expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    Some(t) => Err(QueryError::UnexpectedToken(t.to_string())),
+                    None => Err(QueryError::UnexpectedEnd),
+                }
+            }
+            Some(t) => Err(QueryError::UnexpectedToken(t.to_string())),
+            None => Err(QueryError::UnexpectedEnd),
+        }
+    }
+}
+
+impl Query {
+    fn fmt_operand(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Query::Word(w) => write!(f, "{}", w),
+            _ => write!(f, "({})", self),
+        }
+    }
+
+    fn fmt_binary(x: &Query, op: &str, y: &Query, f: &mut fmt::Formatter) -> fmt::Result {
+        x.fmt_operand(f)?;
+        write!(f, " {} ", op)?;
+        y.fmt_operand(f)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Query::Word(w) => write!(f, "{}", w),
+            Query::Union(x, y) => Query::fmt_binary(x, "|", y, f),
+            Query::Intersection(x, y) => Query::fmt_binary(x, "&", y, f),
+            Query::Difference(x, y) => Query::fmt_binary(x, "-", y, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_query() {
+        assert!(!is_query("uh-huh"));
+        assert!(is_query("house - big"));
+
+        let parsed = parse("(poetry | cat) & uh-huh - big").unwrap();
+        assert_eq!("((poetry | cat) & uh-huh) - big", parsed.to_string());
+
+        assert_eq!(Err(QueryError::UnexpectedEnd), parse("poetry |"));
+        assert_eq!(Err(QueryError::UnexpectedToken(")".to_owned())), parse("poetry)"));
+    }
+}
@@ -0,0 +1,158 @@
+// Browsing the dictionary by concept rather than English prefix.
+//
+// Each toki pona word cuts the English words in two, those whose translation contains it and those that don't.
+// A region fixes which side of some of those cuts we're on, eg must contain "toki" and "musi" but not "ala".
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::{Pu, TokiPonaWord};
+use crate::word_set::WordSet;
+use crate::{TokiSama, TranslationSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Region {
+    pub must: WordSet,
+    pub must_not: WordSet,
+}
+
+impl Region {
+    // Parse "toki musi -ala", words prefixed with '-' must not be in the translation.
+    // Returns the first unknown word on failure.
+    pub fn parse<'a>(s: &'a str, pu: &Pu) -> Result<Self, &'a str> {
+        let mut region = Region::default();
+
+        for token in s.split_whitespace() {
+            let (word, set) = match token.strip_prefix('-') {
+                Some(word) => (word, &mut region.must_not),
+                None => (token, &mut region.must),
+            };
+
+            set.insert(pu.lookup(word).ok_or(word)?);
+        }
+
+        Ok(region)
+    }
+
+    pub fn contains(&self, words: &WordSet) -> bool {
+        self.must.is_subset(words) && words.intersection(&self.must_not).is_empty()
+    }
+
+    // Move each of the given words to the other side of its cut.
+    fn flip(&self, words: &[TokiPonaWord]) -> Self {
+        let mut flipped = *self;
+        for word in words {
+            if (self.must.contains(*word)) {
+                flipped.must.remove(*word);
+                flipped.must_not.insert(*word);
+            }
+            else {
+                flipped.must_not.remove(*word);
+                flipped.must.insert(*word);
+            }
+        }
+
+        flipped
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionEntry {
+    english: String,
+    toki_pona_string: String,
+    source: TranslationSource,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionCount {
+    must: Vec<String>,
+    must_not: Vec<String>,
+    // Words whose side of the cut differs from the region searched from
+    flipped: Vec<String>,
+    count: usize,
+}
+
+impl TokiSama {
+    // Every allowed entry inside a region, best ranked first.
+    pub fn region_entries(&self, region: &Region, options: &SearchOptions, pu: &Pu) -> Vec<RegionEntry> {
+        self.dictionary.entries.iter()
+            .filter(|e| region.contains(&e.toki_pona.words) && options.allows(e))
+            .map(|e| RegionEntry {
+                english: e.english.clone(),
+                toki_pona_string: e.toki_pona.to_string(pu),
+                source: e.source,
+            })
+            .collect()
+    }
+
+    fn region_count(&self, region: &Region, options: &SearchOptions) -> usize {
+        self.dictionary.entries.iter()
+            .filter(|e| region.contains(&e.toki_pona.words) && options.allows(e))
+            .count()
+    }
+
+    // The region itself and every region reached by flipping up to radius of its constrained words,
+    // with how many entries each holds. Fewest flips first, then most populated.
+    pub fn nearby_regions(&self, region: &Region, radius: usize, options: &SearchOptions, pu: &Pu) -> Vec<RegionCount> {
+        let constrained: Vec<TokiPonaWord> = region.must.union(&region.must_not).iter().collect();
+        let names = |words: &mut dyn Iterator<Item = TokiPonaWord>| words.map(|w| pu.get(&w).to_owned()).collect();
+
+        let mut regions = Vec::new();
+        let mut flips = Vec::new();
+        subsets(&constrained, radius.min(constrained.len()), &mut flips, &mut |flipped_words| {
+            let flipped = region.flip(flipped_words);
+            regions.push(RegionCount {
+                must: names(&mut flipped.must.iter()),
+                must_not: names(&mut flipped.must_not.iter()),
+                flipped: names(&mut flipped_words.iter().cloned()),
+                count: self.region_count(&flipped, options),
+            });
+        });
+
+        regions.sort_by(|x, y| x.flipped.len().cmp(&y.flipped.len()).then(y.count.cmp(&x.count)));
+        regions
+    }
+}
+
+// Call f with every subset of words with at most max_size elements.
+fn subsets(words: &[TokiPonaWord], max_size: usize, chosen: &mut Vec<TokiPonaWord>, f: &mut impl FnMut(&[TokiPonaWord])) {
+    f(chosen);
+
+    if (chosen.len() == max_size) {
+        return;
+    }
+
+    for (i, word) in words.iter().enumerate() {
+        chosen.push(*word);
+        subsets(&words[i + 1..], max_size, chosen, f);
+        chosen.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn region_enumeration() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("ala", "no"), ("lipu", "paper")]);
+        let lines = [
+            "toki musi: [poetry 50, joke 40]",
+            "toki musi ala: [boredom 10]",
+            "lipu toki musi: [comic 20]",
+            "toki: [talk 90]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let options = SearchOptions::default();
+
+        let region = Region::parse("toki musi -ala", &pu).unwrap();
+        assert_eq!(3, toki_sama.region_entries(&region, &options, &pu).len());
+        assert_eq!(Err("nimi"), Region::parse("toki -nimi", &pu));
+
+        // The region itself, then flipping each of toki, musi and ala
+        assert_eq!(4, toki_sama.nearby_regions(&region, 1, &options, &pu).len());
+        assert_eq!(8, toki_sama.nearby_regions(&region, 5, &options, &pu).len());
+    }
+}
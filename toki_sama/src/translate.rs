@@ -0,0 +1,123 @@
+// A rough English to toki pona translator, a first draft for learners rather than anything fluent.
+// Each English word is looked up on its own and replaced by its best translation,
+// so "the big dog is sleeping" comes out as "suli soweli lape" with no grammar at all.
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::Pu;
+use crate::{TokiSama, TranslationSource};
+
+// English words that toki pona gets by without, mostly articles, copulas and auxiliaries
+pub(crate) const FUNCTION_WORDS: [&str; 22] = [
+    "a", "an", "the",
+    "am", "is", "are", "was", "were", "be", "been", "being",
+    "do", "does", "did", "will", "would", "shall",
+    "of", "that", "which", "than", "'s",
+];
+
+// Other translations offered per word, besides the best
+const ALTERNATIVES: usize = 3;
+
+#[derive(Debug, Serialize)]
+pub struct TranslatedWord {
+    english: String,
+    // None if there's no translation, the English is passed through
+    pub(crate) toki_pona_string: Option<String>,
+    weight: u32,
+    source: Option<TranslationSource>,
+    pub(crate) alternatives: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SentenceTranslation {
+    pub(crate) toki_pona_string: String,
+    pub(crate) words: Vec<TranslatedWord>,
+}
+
+fn tokenize(sentence: &str) -> Vec<String> {
+    sentence.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .map(|x| x.trim_matches('\'').to_lowercase())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+impl TokiSama {
+    pub fn translate(&self, sentence: &str, options: &SearchOptions, pu: &Pu) -> SentenceTranslation {
+        let words: Vec<TranslatedWord> = tokenize(sentence)
+            .into_iter()
+            .filter(|word| !FUNCTION_WORDS.contains(&word.as_str()))
+            .map(|word| self.translate_word(word, options, pu))
+            .collect();
+
+        let toki_pona: Vec<&str> = words.iter()
+            .map(|w| w.toki_pona_string.as_deref().unwrap_or(&w.english))
+            .collect();
+
+        SentenceTranslation {
+            toki_pona_string: toki_pona.join(" "),
+            words,
+        }
+    }
+
+    fn translate_word(&self, english: String, options: &SearchOptions, pu: &Pu) -> TranslatedWord {
+        // Heaviest first whatever the source, ties keep the entry ranking so the more trusted source wins
+        let mut entries: Vec<usize> = self.exact_entries(&english)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|i| options.allows(&self.dictionary.entries[*i]))
+            .collect();
+        entries.sort_by_key(|i| std::cmp::Reverse(self.dictionary.entries[*i].weight));
+
+        let mut translations: Vec<String> = Vec::new();
+        for i in &entries {
+            let toki_pona = self.dictionary.entries[*i].toki_pona.to_string(pu);
+            if (!translations.contains(&toki_pona)) {
+                translations.push(toki_pona);
+            }
+        }
+
+        let best = entries.first().map(|i| &self.dictionary.entries[*i]);
+        let mut translations = translations.into_iter();
+
+        TranslatedWord {
+            toki_pona_string: translations.next(),
+            weight: best.map(|e| e.weight).unwrap_or(0),
+            source: best.map(|e| e.source),
+            alternatives: translations.take(ALTERNATIVES).collect(),
+            english,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{entries, toki_sama};
+    use crate::Dictionary;
+
+    #[test]
+    fn translate_sentence() {
+        let pu = Pu::from_subset(&[("soweli", "animal"), ("suli", "big"), ("lape", "sleep"), ("mu", "animal noise")]);
+        let lines = [
+            "soweli: [dog 90]",
+            "soweli mu: [dog 40]",
+            "suli: [big 80]",
+            "lape: [sleep 70]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let translated = toki_sama.translate("The big dog is sleeping, Sonja!", &SearchOptions::default(), &pu);
+        assert_eq!("suli soweli lape sonja", translated.toki_pona_string);
+        assert_eq!(vec!["soweli mu".to_owned()], translated.words[1].alternatives);
+        assert_eq!(None, translated.words[3].toki_pona_string);
+
+        // A heavier compound beats a lighter entry from a more trusted source
+        let mut entries = entries(&pu, &["soweli: [dog 30]", "soweli mu: [dog 40]"]);
+        entries[0].source = TranslationSource::NimiPu;
+        let toki_sama = TokiSama::new(Dictionary { entries });
+        let translated = toki_sama.translate("dog", &SearchOptions::default(), &pu);
+        assert_eq!(Some("soweli mu".to_owned()), translated.words[0].toki_pona_string);
+        assert_eq!(vec!["soweli".to_owned()], translated.words[0].alternatives);
+    }
+}
@@ -0,0 +1,331 @@
+// A character trie over English words.
+// We walk it ourselves rather than using radix_trie so we can
+// run an edit distance automaton over it for typo tolerant search.
+//
+// Each node also keeps the smallest value below it, so values with a prefix
+// can be walked smallest first without visiting the whole sub-trie.
+// Callers can walk them in order of their own scores too, given a bound for each node.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+#[derive(Debug)]
+pub struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug)]
+struct TrieNode {
+    // Sorted by char
+    children: Vec<(char, usize)>,
+    value: Option<usize>,
+    // Smallest value of this node or any descendant
+    min_value: usize,
+}
+
+impl Default for TrieNode {
+    fn default() -> Self {
+        TrieNode {
+            children: Vec::new(),
+            value: None,
+            min_value: usize::MAX,
+        }
+    }
+}
+
+// Values with a prefix in increasing order, see Trie::ordered_values.
+pub struct OrderedValues<'a> {
+    trie: &'a Trie,
+    // Nodes still to expand and values found but not yet returned, keyed by the smallest value they can give
+    frontier: BinaryHeap<Reverse<(usize, Frontier)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Frontier {
+    // Ordered before nodes so ordered_values returns a value before expanding a subtree with the same minimum
+    Value(usize),
+    Node(usize),
+}
+
+// Values with a prefix in decreasing order of score, see Trie::best_first.
+pub struct BestFirst<'a, B, S> {
+    trie: &'a Trie,
+    node_bound: B,
+    value_score: S,
+    frontier: BinaryHeap<(Score, Frontier)>,
+}
+
+// An f64 that can go in a heap
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<'a, B: Fn(usize) -> f64, S: Fn(usize) -> f64> Iterator for BestFirst<'a, B, S> {
+    // (value, score)
+    type Item = (usize, f64);
+
+    fn next(&mut self) -> Option<(usize, f64)> {
+        while let Some((Score(score), item)) = self.frontier.pop() {
+            let node = match item {
+                Frontier::Value(value) => return Some((value, score)),
+                Frontier::Node(node) => &self.trie.nodes[node],
+            };
+
+            if let Some(value) = node.value {
+                self.frontier.push((Score((self.value_score)(value)), Frontier::Value(value)));
+            }
+
+            for (_, child) in &node.children {
+                self.frontier.push((Score((self.node_bound)(*child)), Frontier::Node(*child)));
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a> Iterator for OrderedValues<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some(Reverse((_, item))) = self.frontier.pop() {
+            let node = match item {
+                Frontier::Value(value) => return Some(value),
+                Frontier::Node(node) => &self.trie.nodes[node],
+            };
+
+            if let Some(value) = node.value {
+                self.frontier.push(Reverse((value, Frontier::Value(value))));
+            }
+
+            for (_, child) in &node.children {
+                self.frontier.push(Reverse((self.trie.nodes[*child].min_value, Frontier::Node(*child))));
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub key: String,
+    pub value: usize,
+    // Edits needed to turn the query into a prefix of the key
+    pub edits: u32,
+    // The prefix of the key the query was closest to
+    pub corrected: String,
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+
+    fn child(&self, node: usize, c: char) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        children.binary_search_by(|(x, _)| x.cmp(&c)).ok().map(|i| children[i].1)
+    }
+
+    fn find_node(&self, key: &str) -> Option<usize> {
+        let mut node = 0;
+        for c in key.chars() {
+            node = self.child(node, c)?;
+        }
+
+        Some(node)
+    }
+
+    pub fn insert(&mut self, key: &str, value: usize) {
+        let mut node = 0;
+        self.nodes[node].min_value = self.nodes[node].min_value.min(value);
+        for c in key.chars() {
+            node = match self.nodes[node].children.binary_search_by(|(x, _)| x.cmp(&c)) {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let new_node = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node].children.insert(i, (c, new_node));
+                    new_node
+                }
+            };
+
+            self.nodes[node].min_value = self.nodes[node].min_value.min(value);
+        }
+
+        self.nodes[node].value = Some(value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<usize> {
+        self.nodes[self.find_node(key)?].value
+    }
+
+    // Every (key, value) with the given prefix, in key order.
+    pub fn descendants(&self, prefix: &str) -> Vec<(String, usize)> {
+        let mut found = Vec::new();
+        if let Some(node) = self.find_node(prefix) {
+            let mut key = prefix.to_owned();
+            self.collect(node, &mut key, &mut |key, value| found.push((key.to_owned(), value)));
+        }
+
+        found
+    }
+
+    // Every value with the given prefix, smallest first.
+    // Lazy, taking the first few only expands the nodes on the way to them.
+    pub fn ordered_values(&self, prefix: &str) -> OrderedValues<'_> {
+        let mut frontier = BinaryHeap::new();
+        if let Some(node) = self.find_node(prefix) {
+            frontier.push(Reverse((self.nodes[node].min_value, Frontier::Node(node))));
+        }
+
+        OrderedValues { trie: self, frontier }
+    }
+
+    // Every value with the given prefix, highest scoring first.
+    // node_bound(node) must be at least the score of any value at or below the node,
+    // nodes are numbered as in subtree_max.
+    pub fn best_first<B: Fn(usize) -> f64, S: Fn(usize) -> f64>(&self, prefix: &str, node_bound: B, value_score: S) -> BestFirst<'_, B, S> {
+        let mut frontier = BinaryHeap::new();
+        if let Some(node) = self.find_node(prefix) {
+            frontier.push((Score(node_bound(node)), Frontier::Node(node)));
+        }
+
+        BestFirst { trie: self, node_bound, value_score, frontier }
+    }
+
+    // For each node, the combination of leaf(value) over the values at or below it.
+    // Nodes with no values below them get empty.
+    pub fn subtree_max<T: Copy>(&self, leaf: impl Fn(usize) -> T, combine: impl Fn(T, T) -> T, empty: T) -> Vec<T> {
+        let mut maxima = vec![empty; self.nodes.len()];
+
+        // Children are always pushed after their parent, so walking backwards sees them first
+        for node in (0..self.nodes.len()).rev() {
+            let mut max = self.nodes[node].value.map(&leaf).unwrap_or(empty);
+            for (_, child) in &self.nodes[node].children {
+                max = combine(max, maxima[*child]);
+            }
+
+            maxima[node] = max;
+        }
+
+        maxima
+    }
+
+    fn collect(&self, node: usize, key: &mut String, f: &mut impl FnMut(&str, usize)) {
+        if let Some(value) = self.nodes[node].value {
+            f(key, value);
+        }
+
+        for (c, child) in &self.nodes[node].children {
+            key.push(*c);
+            self.collect(*child, key, f);
+            key.pop();
+        }
+    }
+
+    // Keys that have a prefix within max_edits of the query (Levenshtein distance).
+    // Walks the trie keeping one row of the edit distance table per depth,
+    // and gives up on a branch once every cell in the row is over budget.
+    pub fn fuzzy_prefix(&self, query: &str, max_edits: u32) -> Vec<FuzzyMatch> {
+        let query: Vec<char> = query.chars().collect();
+        let first_row: Vec<u32> = (0..=query.len() as u32).collect();
+        let mut found = Vec::new();
+        let mut key = String::new();
+
+        let best = (first_row[query.len()], 0);
+        self.fuzzy_walk(0, &query, &first_row, best, max_edits, &mut key, &mut found);
+
+        found
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fuzzy_walk(&self,
+                  node: usize,
+                  query: &[char],
+                  row: &[u32],
+                  best: (u32, usize),
+                  max_edits: u32,
+                  key: &mut String,
+                  found: &mut Vec<FuzzyMatch>) {
+        let (best_edits, best_len) = best;
+
+        if (best_edits <= max_edits) {
+            if let Some(value) = self.nodes[node].value {
+                found.push(FuzzyMatch {
+                    key: key.clone(),
+                    value,
+                    edits: best_edits,
+                    corrected: key[..best_len].to_owned(),
+                });
+            }
+        }
+        else if (row.iter().all(|x| *x > max_edits)) {
+            return;
+        }
+
+        for (c, child) in &self.nodes[node].children {
+            let mut next_row = Vec::with_capacity(row.len());
+            next_row.push(row[0] + 1);
+            for j in 1..row.len() {
+                let substitute = row[j - 1] + if (query[j - 1] == *c) { 0 } else { 1 };
+                next_row.push(substitute.min(row[j] + 1).min(next_row[j - 1] + 1));
+            }
+
+            key.push(*c);
+            let edits = next_row[query.len()];
+            let next_best = if (edits < best_edits) { (edits, key.len()) } else { best };
+            self.fuzzy_walk(*child, query, &next_row, next_best, max_edits, key, found);
+            key.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_prefix_search() {
+        let mut trie = Trie::new();
+        for (i, word) in ["teacher", "teach", "tea", "jellyfish", "jelly"].iter().enumerate() {
+            trie.insert(word, i);
+        }
+
+        assert_eq!(vec![("tea".to_owned(), 2), ("teach".to_owned(), 1), ("teacher".to_owned(), 0)], trie.descendants("tea"));
+        assert_eq!(vec![0, 1, 2], trie.ordered_values("tea").collect::<Vec<_>>());
+        assert_eq!(Some(3), trie.ordered_values("j").next());
+
+        let matches = trie.fuzzy_prefix("teacer", 1);
+        assert_eq!(1, matches.len());
+        assert_eq!("teacher", matches[0].key);
+        assert_eq!("teacher", matches[0].corrected);
+        assert_eq!(1, matches[0].edits);
+
+        let matches = trie.fuzzy_prefix("jellyfsh", 1);
+        assert_eq!(vec!["jellyfish"], matches.iter().map(|m| m.key.as_str()).collect::<Vec<_>>());
+
+        assert!(trie.fuzzy_prefix("jellyfsh", 0).is_empty());
+    }
+}
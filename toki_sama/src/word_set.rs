@@ -0,0 +1,114 @@
+use crate::pu::TokiPonaWord;
+
+const BLOCKS: usize = 8;
+
+// A set of toki pona words as a bitset, one bit per TokiPonaWord.
+// Fixed size so sets stay Copy, Pu refuses to load more than CAPACITY words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WordSet {
+    bits: [u64; BLOCKS],
+}
+
+impl WordSet {
+    pub const CAPACITY: usize = BLOCKS * 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_words(words: &[TokiPonaWord]) -> Self {
+        let mut set = Self::new();
+        for word in words {
+            set.insert(*word);
+        }
+
+        set
+    }
+
+    pub fn insert(&mut self, word: TokiPonaWord) {
+        let i = word.index();
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn remove(&mut self, word: TokiPonaWord) {
+        let i = word.index();
+        self.bits[i / 64] &= !(1 << (i % 64));
+    }
+
+    pub fn contains(&self, word: TokiPonaWord) -> bool {
+        let i = word.index();
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|x| *x == 0)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |x, y| x | y)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |x, y| x & y)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |x, y| x & !y)
+    }
+
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |x, y| x ^ y)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    // Size of the symmetric difference, without building it.
+    pub fn dist(&self, other: &Self) -> u32 {
+        self.bits.iter().zip(other.bits.iter()).map(|(x, y)| (x ^ y).count_ones()).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = TokiPonaWord> + '_ {
+        (0..BLOCKS * 64)
+            .filter(move |i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+            .map(TokiPonaWord::from_index)
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        let mut bits = [0; BLOCKS];
+        for (i, block) in bits.iter_mut().enumerate() {
+            *block = f(self.bits[i], other.bits[i]);
+        }
+
+        WordSet { bits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pu::Pu;
+
+    #[test]
+    fn word_set_algebra() {
+        let pu = Pu::from_subset(&[("toki", "talk"), ("musi", "fun"), ("soweli", "animal")]);
+        let toki = pu.lookup("toki").unwrap();
+        let musi = pu.lookup("musi").unwrap();
+        let soweli = pu.lookup("soweli").unwrap();
+
+        let poetry = WordSet::from_words(&[toki, musi]);
+        let pet = WordSet::from_words(&[soweli, musi]);
+
+        assert_eq!(2, poetry.dist(&pet));
+        assert_eq!(vec![musi], poetry.intersection(&pet).iter().collect::<Vec<_>>());
+        assert_eq!(3, poetry.union(&pet).len());
+        assert_eq!(vec![toki], poetry.difference(&pet).iter().collect::<Vec<_>>());
+        assert!(WordSet::from_words(&[musi]).is_subset(&poetry));
+        assert!(!pet.is_subset(&poetry));
+    }
+}
@@ -0,0 +1,216 @@
+// Coining toki pona terms for new concepts from a few describing words.
+//
+//   smartphone: tool, talk, small -> ilo toki lili, ilo toki, ...
+//
+// Each describing word is translated, and compounds are built from the words of those
+// translations with a noun as the head. Candidates covering more of the description score
+// higher, and every candidate lists the English words already translated near it.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::options::SearchOptions;
+use crate::pu::{PartOfSpeech, Pu, TokiPonaWord};
+use crate::query::QueryError;
+use crate::{CompoundWord, ThesaurusResult, TokiSama, WordGloss};
+
+// Translations of each describing word that words are taken from
+const TRANSLATIONS_PER_WORD: usize = 3;
+
+// Most toki pona words considered across the whole description
+const MAX_POOL: usize = 8;
+
+// Most modifiers after the head
+const MAX_MODIFIERS: usize = 2;
+
+// Score lost per word after the head, so shorter compounds win ties
+const LENGTH_PENALTY: f64 = 0.1;
+
+#[derive(Debug, Serialize)]
+pub struct Coinage {
+    pub(crate) toki_pona_string: String,
+    breakdown: Vec<WordGloss>,
+    score: f64,
+    // The describing words the compound has a word for
+    pub(crate) covers: Vec<String>,
+    // English words already translated close to the compound
+    pub(crate) clashes: Vec<ThesaurusResult>,
+}
+
+// How well each candidate word expresses each describing word, from 0 to 1
+struct Pool {
+    words: Vec<TokiPonaWord>,
+    // scores[d][w] for describing word d and pool word w
+    scores: Vec<Vec<f64>>,
+}
+
+impl Pool {
+    fn score(&self, compound: &[usize], describing: &[&str]) -> (f64, Vec<String>) {
+        let mut total = 0.0;
+        let mut covers = Vec::new();
+
+        for (d, scores) in self.scores.iter().enumerate() {
+            let best = compound.iter().map(|w| scores[*w]).fold(0.0, f64::max);
+            if (best > 0.0) {
+                covers.push(describing[d].to_owned());
+            }
+
+            total += best;
+        }
+
+        let coverage = total / self.scores.len() as f64;
+        (coverage - LENGTH_PENALTY * (compound.len() - 1) as f64, covers)
+    }
+}
+
+impl TokiSama {
+    // Candidate compounds for a concept described by a few English words, best first.
+    // Errors with the first describing word that has no translation.
+    pub fn coin(&self, describing: &[&str], options: &SearchOptions, pu: &Pu) -> Result<Vec<Coinage>, QueryError> {
+        let pool = self.word_pool(describing, options)?;
+
+        // A head that can be a noun, otherwise anything will do
+        let is_noun = |w: &usize| pu.senses(&pool.words[*w]).iter().any(|s| s.part_of_speech == Some(PartOfSpeech::Noun));
+        let mut heads: Vec<usize> = (0..pool.words.len()).filter(is_noun).collect();
+        if (heads.is_empty()) {
+            heads = (0..pool.words.len()).collect();
+        }
+
+        let mut candidates = Vec::new();
+        for head in heads {
+            let others: Vec<usize> = (0..pool.words.len()).filter(|w| *w != head).collect();
+            let mut modifiers = Vec::new();
+            modifier_subsets(&others, &mut modifiers, &mut |modifiers| {
+                let mut compound = vec![head];
+                compound.extend_from_slice(modifiers);
+
+                let (score, covers) = pool.score(&compound, describing);
+                candidates.push((compound, score, covers));
+            });
+        }
+
+        candidates.sort_by(|(x, score_x, _), (y, score_y, _)| score_y.partial_cmp(score_x).unwrap().then(x.cmp(y)));
+
+        // The same words with a different head are only worth showing once, the stronger head wins
+        let mut seen = HashSet::new();
+        candidates.retain(|(compound, _, _)| {
+            let mut words = compound.clone();
+            words.sort();
+            seen.insert(words)
+        });
+
+        let coinages = candidates.into_iter()
+            .take(options.completions)
+            .map(|(compound, score, covers)| {
+                let toki_pona = CompoundWord::new(compound.iter().map(|w| pool.words[*w]).collect());
+                Coinage {
+                    toki_pona_string: toki_pona.to_string(pu),
+                    breakdown: toki_pona.glosses(pu),
+                    score,
+                    covers,
+                    clashes: self.find_similar(&toki_pona, options, pu),
+                }
+            })
+            .collect();
+
+        Ok(coinages)
+    }
+
+    fn word_pool(&self, describing: &[&str], options: &SearchOptions) -> Result<Pool, QueryError> {
+        let mut weighted: Vec<Vec<(TokiPonaWord, f64)>> = Vec::new();
+
+        for english in describing {
+            let entries: Vec<usize> = self.exact_entries(english)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|i| options.allows(&self.dictionary.entries[*i]))
+                .take(TRANSLATIONS_PER_WORD)
+                .collect();
+
+            // The first entry is from the most trusted source, not always the heaviest
+            let top_weight = match entries.first() {
+                Some(i) => self.dictionary.entries[*i].weight.max(1) as f64,
+                None => return Err(QueryError::UnknownWord(english.to_string())),
+            };
+
+            // A word scores as its best translation relative to the describing word's best,
+            // shared with the other words in that translation
+            let mut word_weights: Vec<(TokiPonaWord, f64)> = Vec::new();
+            for i in entries {
+                let entry = &self.dictionary.entries[i];
+                let weight = (entry.weight as f64 / top_weight).min(1.0) / entry.toki_pona.words.len().max(1) as f64;
+                for tp in entry.toki_pona.content_words() {
+                    match word_weights.iter_mut().find(|(x, _)| *x == tp) {
+                        Some((_, w)) => *w = w.max(weight),
+                        None => word_weights.push((tp, weight)),
+                    }
+                }
+            }
+
+            weighted.push(word_weights);
+        }
+
+        // Keep the strongest words overall, modifiers are ordered by strength too
+        let mut strongest: Vec<(TokiPonaWord, f64)> = weighted.iter().flatten().cloned().collect();
+        strongest.sort_by(|(_, x), (_, y)| y.partial_cmp(x).unwrap());
+
+        let mut words: Vec<TokiPonaWord> = Vec::new();
+        for (tp, _) in strongest {
+            if (words.len() < MAX_POOL && !words.contains(&tp)) {
+                words.push(tp);
+            }
+        }
+
+        let scores = weighted.iter()
+            .map(|word_weights| words.iter().map(|tp| {
+                word_weights.iter().find(|(x, _)| x == tp).map(|(_, w)| *w).unwrap_or(0.0)
+            }).collect())
+            .collect();
+
+        Ok(Pool { words, scores })
+    }
+}
+
+// Call f with every subset of others with at most MAX_MODIFIERS elements, in order.
+fn modifier_subsets(others: &[usize], chosen: &mut Vec<usize>, f: &mut impl FnMut(&[usize])) {
+    f(chosen);
+
+    if (chosen.len() == MAX_MODIFIERS) {
+        return;
+    }
+
+    for (i, other) in others.iter().enumerate() {
+        chosen.push(*other);
+        modifier_subsets(&others[i + 1..], chosen, f);
+        chosen.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::toki_sama;
+
+    #[test]
+    fn coin_compounds() {
+        let pu = Pu::from_subset(&[("ilo", "tool"), ("toki", "talk"), ("lili", "small"), ("kala", "fish")]);
+        let lines = [
+            "ilo: [tool 90]",
+            "toki: [talk 90]",
+            "lili: [small 90]",
+            "ilo toki: [phone 70]",
+            "kala lili: [small 10]",
+        ];
+
+        let toki_sama = toki_sama(&pu, &lines);
+        let coinages = toki_sama.coin(&["tool", "talk", "small"], &SearchOptions::default(), &pu).unwrap();
+
+        // Without parts of speech in the subset every word can be a head
+        assert_eq!("ilo toki lili", coinages[0].toki_pona_string);
+        assert_eq!(vec!["tool", "talk", "small"], coinages[0].covers);
+        assert_eq!("phone", coinages[0].clashes[0].english);
+
+        assert_eq!(Err(QueryError::UnknownWord("zebra".to_owned())), toki_sama.coin(&["zebra"], &SearchOptions::default(), &pu).map(|_| ()));
+    }
+}
@@ -4,7 +4,7 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
-use toki_sama::{*, pu::*};
+use toki_sama::{*, pu::*, options::SearchOptions, region::Region, antonym::Opposites, popularity::{Frequencies, RankingWeights}};
 
 fn read_wordset(path: &Path, pu: &Pu, source: TranslationSource) -> Dictionary {
     let file = File::open(path).unwrap();
@@ -69,11 +69,176 @@ fn get_data_path() -> PathBuf {
     path
 }
 
+fn parse_source(s: &str) -> Option<TranslationSource> {
+    match s {
+        "nimi_pu" => Some(TranslationSource::NimiPu),
+        "compounds" => Some(TranslationSource::Compounds),
+        "generated" => Some(TranslationSource::Generated),
+        _ => None,
+    }
+}
+
+// "auto" for options that otherwise scale with the search
+fn parse_auto(value: &str) -> Option<Option<u32>> {
+    if value == "auto" {
+        Some(None)
+    }
+    else {
+        value.parse().ok().map(Some)
+    }
+}
+
+fn parse_weight(value: &str) -> Option<f64> {
+    value.parse().ok().filter(|x| RankingWeights::is_valid_weight(*x))
+}
+
+fn set_option(options: &mut SearchOptions, key: &str, value: &str) -> Option<()> {
+    match key {
+        "completions" => options.completions = value.parse().ok()?,
+        "similar" => options.similar = value.parse().ok()?,
+        "max_dist" => options.max_dist = parse_auto(value)?,
+        "max_edits" => options.max_edits = parse_auto(value)?,
+        "metric" => options.metric = value.parse().ok()?,
+        "sources" => options.sources = value.split(',').map(|x| parse_source(x.trim())).collect::<Option<_>>()?,
+        "min_weight" => options.min_weight = value.parse().ok()?,
+        "categories" => options.categories = value.split(',').map(|x| x.trim().parse().ok()).collect::<Option<_>>()?,
+        "min_len" => options.min_len = value.parse().ok()?,
+        "max_len" => options.max_len = value.parse().ok()?,
+        "popularity_weight" => options.ranking.popularity = parse_weight(value)?,
+        "weight_weight" => options.ranking.weight = parse_weight(value)?,
+        "source_weight" => options.ranking.source = parse_weight(value)?,
+        "diversity" => options.diversity = value.parse().ok()?,
+        _ => return None,
+    }
+
+    Some(())
+}
+
+fn run_command(command: &str, toki_sama: &TokiSama, pu: &Pu, opposites: &Opposites, options: &mut SearchOptions) {
+    let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+
+    match name {
+        "reverse" => {
+            match toki_sama.reverse_lookup(args, options, pu) {
+                Ok(res) => {
+                    for r in res {
+                        println!("{:#?}", r);
+                    }
+                }
+                Err(word) => println!("Unknown toki pona word {}", word),
+            }
+        }
+        "ladder" => {
+            let words: Vec<&str> = args.split_whitespace().collect();
+            let max_step = words.get(2).and_then(|x| x.parse().ok()).unwrap_or(2);
+            match (words.first(), words.get(1)) {
+                (Some(from), Some(to)) => {
+                    match toki_sama.ladder(from, to, max_step, options, pu) {
+                        Some(steps) => {
+                            for step in steps {
+                                println!("{:?}", step);
+                            }
+                        }
+                        None => println!("No ladder from {} to {}", from, to),
+                    }
+                }
+                _ => println!("Usage: /ladder <from> <to> [max step]"),
+            }
+        }
+        "antonym" => {
+            match toki_sama.antonyms(args.trim(), opposites, options, pu) {
+                Some(antonyms) => {
+                    for antonym in antonyms {
+                        println!("{:#?}", antonym);
+                    }
+                }
+                None => println!("Unknown word {}", args),
+            }
+        }
+        "gloss" => {
+            for token in toki_sama.gloss(args, pu) {
+                println!("{:?}", token);
+            }
+        }
+        "translate" => {
+            println!("{:#?}", toki_sama.translate(args, options, pu));
+        }
+        "coin" => {
+            let describing: Vec<&str> = args.split(|c: char| c == ',' || c.is_whitespace()).filter(|x| !x.is_empty()).collect();
+            match toki_sama.coin(&describing, options, pu) {
+                Ok(coinages) => {
+                    for coinage in coinages {
+                        println!("{:#?}", coinage);
+                    }
+                }
+                Err(e) => println!("Could not coin a word: {}", e),
+            }
+        }
+        "region" => {
+            match Region::parse(args, pu) {
+                Ok(region) => {
+                    let entries = toki_sama.region_entries(&region, options, pu);
+                    for entry in &entries {
+                        println!("{:?}", entry);
+                    }
+                    println!("{} entries", entries.len());
+                }
+                Err(word) => println!("Unknown toki pona word {}", word),
+            }
+        }
+        "nearby" => {
+            let (radius, words) = args.split_once(' ').unwrap_or((args, ""));
+            match (radius.parse(), Region::parse(words, pu)) {
+                (Ok(radius), Ok(region)) => {
+                    for count in toki_sama.nearby_regions(&region, radius, options, pu) {
+                        println!("{:?}", count);
+                    }
+                }
+                (Err(_), _) => println!("Usage: /nearby <radius> <toki pona> [-<toki pona>]"),
+                (_, Err(word)) => println!("Unknown toki pona word {}", word),
+            }
+        }
+        "set" => {
+            let (key, value) = args.split_once(' ').unwrap_or((args, ""));
+            match set_option(options, key, value.trim()) {
+                Some(()) => println!("{:#?}", options),
+                None => println!("Could not set {} to '{}'", key, value),
+            }
+        }
+        "options" => {
+            println!("{:#?}", options);
+        }
+        _ => {
+            println!("Unknown command {}", name);
+            println!("Commands:");
+            println!("  /reverse <toki pona>    English words translated as a toki pona phrase");
+            println!("  /antonym <english>      Words near the translation negated with ala or an opposite pair");
+            println!("  /gloss <toki pona>      Gloss a toki pona sentence word by word");
+            println!("  /translate <english>    Rough word by word translation of an English sentence");
+            println!("  /coin tool, talk, small Propose compounds for a new concept from describing words");
+            println!("  /ladder <from> <to>     Chain of similar words from one English word to another");
+            println!("  /region toki musi -ala  English words whose translation has toki and musi but not ala");
+            println!("  /nearby <radius> <...>  Entry counts of regions flipping up to radius words of a region");
+            println!("  /set <option> <value>   Change a search option, eg /set metric jaccard or /set sources nimi_pu,compounds or /set categories pu,ku_suli or /set popularity_weight 0.8");
+            println!("  /options                Show the current search options");
+        }
+    }
+}
+
 pub fn main() {
     let mut pu_path = get_data_path();
     pu_path.push("pu.csv");
     println!("Reading pu from {:?}...", &pu_path);
-    let pu = Pu::read(&pu_path);
+    let mut pu = Pu::read(&pu_path);
+
+    let mut extra_words_path = get_data_path();
+    extra_words_path.push("extra_words.csv");
+    println!("Reading ku and newer words from {:?}...", &extra_words_path);
+    pu.read_extra(&extra_words_path);
+
+    let mut opposites_path = get_data_path();
+    opposites_path.push("opposites.txt");
+    let opposites = Opposites::read(&opposites_path, &pu);
 
     let mut dict = Dictionary::new();
 
@@ -89,10 +254,23 @@ pub fn main() {
     let model = read_model(&pu);
     dict.merge_with(model);
 
-    let toki_sama = TokiSama::new(dict);
+    // The frequency list is optional, without it completions are ranked by translation weight and source only
+    let mut frequencies_path = get_data_path();
+    frequencies_path.push("english_frequency.txt");
+    let frequencies = if frequencies_path.exists() {
+        println!("Reading English word frequencies from {:?}...", &frequencies_path);
+        Frequencies::read(&frequencies_path)
+    }
+    else {
+        Frequencies::default()
+    };
+
+    let toki_sama = TokiSama::with_frequencies(dict, &frequencies);
 
     println!("\n-- toki sama --\n");
 
+    let mut options = SearchOptions::default();
+
     for m_line in std::io::stdin().lock().lines() {
         let line = m_line.unwrap();
 
@@ -100,7 +278,35 @@ pub fn main() {
             continue;
         }
 
-        let res = toki_sama.lookup(&line, &pu);
+        if let Some(command) = line.strip_prefix('/') {
+            run_command(command, &toki_sama, &pu, &opposites, &mut options);
+            continue;
+        }
+
+        if analogy::is_analogy(&line) {
+            match analogy::parse(&line) {
+                Some((a, b, c)) => {
+                    match toki_sama.analogy(a, b, c, &options, &pu) {
+                        Ok(res) => println!("{:#?}", res),
+                        Err(e) => println!("Could not evaluate analogy: {}", e),
+                    }
+                }
+                None => println!("Usage: king:queen::man:?"),
+            }
+
+            continue;
+        }
+
+        if query::is_query(&line) {
+            match toki_sama.query(&line, &options, &pu) {
+                Ok(c) => println!("{:#?}", c),
+                Err(e) => println!("Could not evaluate query: {}", e),
+            }
+
+            continue;
+        }
+
+        let res = toki_sama.lookup_with_fallback(&line, &options, None, &pu);
         for c in res {
             println!("{:#?}", c);
         }
@@ -14,13 +14,17 @@ var explanation = document.getElementById("explanation");
 Promise.all(
     [
         fetch("pu.csv").then(x => x.text()),
+        fetch("extra_words.csv").then(x => x.text()),
+        fetch("opposites.txt").then(x => x.text()),
         fetch("nimi_pu.txt").then(x => x.text()),
         fetch("compounds.txt").then(x => x.text()),
         fetch("generated_day2.tsv").then(x => x.text()),
+        // Optional, without it completions aren't ranked by popularity
+        fetch("english_frequency.txt").then(x => x.ok ? x.text() : "").catch(() => ""),
     ]
 )
-.then(([pu, nimi_pu, compounds, model]) => {
-    toki_sama = new TokiSamaSearch(pu, nimi_pu, compounds, model);
+.then(([pu, extra_words, opposites, nimi_pu, compounds, model, frequencies]) => {
+    toki_sama = new TokiSamaSearch(pu, extra_words, opposites, nimi_pu, compounds, model, frequencies);
     console.log("Finished search init!");
 
     textfield.removeAttribute("disabled");
@@ -53,6 +57,9 @@ function get_class_by_source(source) {
     else if (source === "Compounds") {
         return "compounds";
     }
+    else if (source === "Synthesized") {
+        return "synthesized";
+    }
     else {
         return "";
     }
@@ -99,7 +106,18 @@ function render(prefix, results_string) {
 
         let title_english = document.createElement("h3");
         title_english.setAttribute("class", "title");
-        title_english.innerHTML = highlight_completion(prefix, result.english_search);
+        if (result.correction) {
+            // Matched after fixing a typo, the prefix highlighting wouldn't line up
+            title_english.innerHTML = result.english_search;
+            title_english.setAttribute("title", "did you mean " + result.correction.did_you_mean + "?");
+        }
+        else if (result.lemma) {
+            // Matched an inflection, show which dictionary word it was
+            title_english.innerHTML = result.english_search + " <i>(" + result.entry_english + ")</i>";
+        }
+        else {
+            title_english.innerHTML = highlight_completion(prefix, result.english_search);
+        }
         english_elem.appendChild(title_english);
 
         let toki_elem = document.createElement("span");
@@ -109,9 +127,18 @@ function render(prefix, results_string) {
 
         title_toki.setAttribute("class", "title " + get_class_by_source(result.source));
         title_toki.setAttribute("title", result.source);
-        title_toki.innerHTML = result.original_translation_string;
+        render_breakdown(title_toki, result.breakdown);
         toki_elem.appendChild(title_toki);
 
+        // Other translations of the same word
+        for (let alternative of result.alternatives) {
+            let alternative_elem = document.createElement("span");
+            alternative_elem.setAttribute("class", "alternative " + get_class_by_source(alternative.source));
+            alternative_elem.setAttribute("title", alternative.source + " " + alternative.weight);
+            render_breakdown(alternative_elem, alternative.breakdown);
+            toki_elem.appendChild(alternative_elem);
+        }
+
         title.appendChild(english_elem);
         title.appendChild(toki_elem);
 
@@ -128,10 +155,11 @@ function render(prefix, results_string) {
             let toki_elem = document.createElement("span");
             toki_elem.setAttribute("class", "item-toki-pona " + get_class_by_source(similar.source));
             toki_elem.setAttribute("title", similar.source);
-            toki_elem.innerHTML = similar.toki_pona_string;
+            render_breakdown(toki_elem, similar.breakdown);
 
             similar_elem.appendChild(english_elem);
             similar_elem.appendChild(toki_elem);
+            similar_elem.setAttribute("title", explain(similar));
 
             card.appendChild(similar_elem);
         }
@@ -140,6 +168,41 @@ function render(prefix, results_string) {
     resultsfield.appendChild(card);
 }
 
+// Show each toki pona word with its definition on hover
+function render_breakdown(elem, breakdown) {
+    breakdown.forEach((word, i) => {
+        if (i > 0) {
+            elem.appendChild(document.createTextNode(" "));
+        }
+
+        let word_elem = document.createElement("span");
+        word_elem.setAttribute("class", "gloss");
+        word_elem.setAttribute("title", ((word.part_of_speech || "") + " " + word.gloss).trim());
+        word_elem.textContent = word.toki_pona;
+        elem.appendChild(word_elem);
+    });
+}
+
+// Describe why a similar word was matched, eg "both are toki musi"
+function explain(similar) {
+    const words = (glosses) => glosses.map(x => x.toki_pona).join(" ");
+    let parts = [];
+
+    if (similar.shared.length > 0) {
+        parts.push("both are " + words(similar.shared));
+    }
+
+    if (similar.only_result.length > 0) {
+        parts.push("adds " + words(similar.only_result));
+    }
+
+    if (similar.only_query.length > 0) {
+        parts.push("without " + words(similar.only_query));
+    }
+
+    return parts.join(", ");
+}
+
 function highlight_completion(prefix, full) {
     let res = prefix;
     const completion =full.substring(prefix.length);
@@ -1,7 +1,10 @@
 use wasm_bindgen::prelude::*;
 
-use toki_sama::{TokiSama, Dictionary, Translation, TranslationSource};
+use toki_sama::{TokiSama, Dictionary, Translation, TranslationSource, query, analogy};
 use toki_sama::pu::Pu;
+use toki_sama::options::SearchOptions;
+use toki_sama::antonym::Opposites;
+use toki_sama::popularity::Frequencies;
 
 macro_rules! log {
     ( $( $t:tt )* ) => {
@@ -50,14 +53,23 @@ fn read_model(model : &str, pu : &Pu) -> Dictionary {
 pub struct TokiSamaSearch {
     toki_sama : TokiSama,
     pu : Pu,
+    opposites : Opposites,
+    options : SearchOptions,
 }
 
 #[wasm_bindgen]
 impl TokiSamaSearch {
     #[wasm_bindgen(constructor)]
-    pub fn new(pu_data : &str, nimi_pu_str : &str, compounds_str : &str, model_str : &str) -> Self {
-        let pu_lines = pu_data.lines().map(|x| x.to_string()).collect();
-        let pu = Pu::from_lines(&pu_lines);
+    // frequencies_str may be empty, completions are then ranked without word popularity.
+    pub fn new(pu_data : &str, extra_words_str : &str, opposites_str : &str, nimi_pu_str : &str, compounds_str : &str, model_str : &str, frequencies_str : &str) -> Self {
+        let pu_lines : Vec<String> = pu_data.lines().map(|x| x.to_string()).collect();
+        let mut pu = Pu::from_lines(&pu_lines);
+
+        let extra_words_lines : Vec<String> = extra_words_str.lines().map(|x| x.to_string()).collect();
+        pu.extend_from_lines(&extra_words_lines);
+
+        let opposites_lines : Vec<String> = opposites_str.lines().map(|x| x.to_string()).collect();
+        let opposites = Opposites::from_lines(&opposites_lines, &pu);
 
         let mut dict = Dictionary::new();
 
@@ -69,22 +81,85 @@ impl TokiSamaSearch {
         let compounds = parse_wordset(compounds_str, &pu, TranslationSource::Compounds);
         dict.merge_with(compounds);
 
-        // TODO improve lookup perf in wasm before enabling this
-        // build vantage point tree or smth
         log!("Reading model");
         let model = read_model(model_str, &pu);
         dict.merge_with(model);
 
-        let toki_sama = TokiSama::new(dict);
+        let frequencies_lines : Vec<String> = frequencies_str.lines().map(|x| x.to_string()).collect();
+        let frequencies = Frequencies::from_lines(&frequencies_lines);
+
+        let toki_sama = TokiSama::with_frequencies(dict, &frequencies);
 
         TokiSamaSearch {
             toki_sama,
             pu,
+            opposites,
+            options : SearchOptions::default(),
+        }
+    }
+
+    // Takes a json SearchOptions, any missing fields are left at their defaults.
+    // Returns false if the options couldn't be parsed or have negative ranking weights.
+    pub fn set_options(&mut self, options_json : &str) -> bool {
+        match serde_json::from_str::<SearchOptions>(options_json) {
+            Ok(options) => {
+                if !options.ranking.is_valid() {
+                    log!("Ranking weights must be non-negative: {:?}", options.ranking);
+                    return false;
+                }
+
+                self.options = options;
+                true
+            }
+            Err(e) => {
+                log!("Could not parse options: {}", e);
+                false
+            }
         }
     }
 
     pub fn search(&self, prefix : &str) -> String {
-        let results = self.toki_sama.lookup(prefix, &self.pu);
+        let results = if query::is_query(prefix) {
+            // Half typed queries are common, just show nothing until they parse
+            self.toki_sama.query(prefix, &self.options, &self.pu).into_iter().collect()
+        }
+        else {
+            // No corpus in the browser, unknown words are only split into parts
+            self.toki_sama.lookup_with_fallback(prefix, &self.options, None, &self.pu)
+        };
+
+        serde_json::to_string(&results).unwrap()
+    }
+
+    pub fn reverse_search(&self, toki_pona : &str) -> String {
+        let results = self.toki_sama.reverse_lookup(toki_pona, &self.options, &self.pu).unwrap_or_default();
         serde_json::to_string(&results).unwrap()
     }
-}
\ No newline at end of file
+
+    pub fn antonym_search(&self, english : &str) -> String {
+        let results = self.toki_sama.antonyms(english, &self.opposites, &self.options, &self.pu).unwrap_or_default();
+        serde_json::to_string(&results).unwrap()
+    }
+
+    // Takes "a:b::c:?", returns null if it doesn't parse or a word is unknown.
+    pub fn analogy_search(&self, input : &str) -> String {
+        let result = analogy::parse(input)
+            .and_then(|(a, b, c)| self.toki_sama.analogy(a, b, c, &self.options, &self.pu).ok());
+        serde_json::to_string(&result).unwrap()
+    }
+
+    pub fn gloss(&self, toki_pona : &str) -> String {
+        serde_json::to_string(&self.toki_sama.gloss(toki_pona, &self.pu)).unwrap()
+    }
+
+    pub fn translate(&self, english : &str) -> String {
+        serde_json::to_string(&self.toki_sama.translate(english, &self.options, &self.pu)).unwrap()
+    }
+
+    // Takes comma separated describing words, returns an empty list if one is unknown.
+    pub fn coin(&self, describing : &str) -> String {
+        let describing : Vec<&str> = describing.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
+        let results = self.toki_sama.coin(&describing, &self.options, &self.pu).unwrap_or_default();
+        serde_json::to_string(&results).unwrap()
+    }
+}